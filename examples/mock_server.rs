@@ -0,0 +1,19 @@
+//! 組み込みのサンプルデータを検索対象とするモックサーバーを起動する
+//!
+//! ```sh
+//! cargo run --example mock_server --features mock -- 127.0.0.1:8080
+//! ```
+
+use crd_api::mock::{Corpus, MockServer};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let server = MockServer::bind(addr, Corpus::fixtures()).await?;
+    println!("listening on {}", server.url());
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
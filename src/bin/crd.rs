@@ -0,0 +1,446 @@
+//! レファ協を検索するコマンドラインツール
+//!
+//! `cli` featureが必要
+//!
+//! ```sh
+//! # 質問に「読書」を含むレファレンス事例を表形式で表示
+//! crd --type reference --query "question any 読書"
+//!
+//! # 簡易検索の結果を全件JSON Lines形式で出力
+//! crd 村上春樹 --all-pages --format jsonl
+//! ```
+
+use std::{error::Error, io::Write, process::ExitCode};
+
+use chrono::NaiveDate;
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    Parser, ValueEnum,
+};
+use crd_api::{
+    client::Client,
+    cql,
+    request::{LibGroup, Request, SearchType, SortKey, SortOrder, MAX_RESULTS_NUM},
+    response::{ResultItem, ResultSet},
+};
+use futures::{StreamExt, TryStreamExt};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// パラメータのenumの値だけを受け付けるパーサー
+macro_rules! param_parser {
+    ($t:ty) => {
+        PossibleValuesParser::new(<$t>::VALUES.iter().map(|v| v.as_str()))
+            .map(|s| s.parse::<$t>().unwrap())
+    };
+}
+
+/// レファレンス協同データベース (レファ協) を検索する
+#[derive(Parser, Debug)]
+#[command(name = "crd", version)]
+struct Args {
+    /// 簡易検索の検索語 (`--query` を指定しない場合)
+    terms: Vec<String>,
+
+    /// 検索区分
+    #[arg(short = 't', long = "type", value_parser = param_parser!(SearchType))]
+    search_type: Option<SearchType>,
+
+    /// CQL形式の検索条件 (例: "question any 本 and solution = 0")
+    #[arg(short, long, conflicts_with = "terms")]
+    query: Option<String>,
+
+    /// 事例作成日 FROM (YYYY-MM-DD または YYYYMMDD)
+    #[arg(long, value_parser = parse_date)]
+    crt_from: Option<NaiveDate>,
+
+    /// 事例作成日 TO
+    #[arg(long, value_parser = parse_date)]
+    crt_to: Option<NaiveDate>,
+
+    /// 登録日 FROM
+    #[arg(long, value_parser = parse_date)]
+    reg_from: Option<NaiveDate>,
+
+    /// 登録日 TO
+    #[arg(long, value_parser = parse_date)]
+    reg_to: Option<NaiveDate>,
+
+    /// 最終更新日 FROM
+    #[arg(long, value_parser = parse_date)]
+    lst_from: Option<NaiveDate>,
+
+    /// 最終更新日 TO
+    #[arg(long, value_parser = parse_date)]
+    lst_to: Option<NaiveDate>,
+
+    /// 提供館コード
+    #[arg(long)]
+    lib_id: Option<String>,
+
+    /// 検索対象
+    #[arg(long, value_parser = param_parser!(LibGroup))]
+    lib_group: Option<LibGroup>,
+
+    /// ソート項目
+    #[arg(long, value_parser = param_parser!(SortKey))]
+    sort: Option<SortKey>,
+
+    /// ソート条件
+    #[arg(long, value_parser = param_parser!(SortOrder))]
+    order: Option<SortOrder>,
+
+    /// 検索結果取得位置
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    position: Option<i32>,
+
+    /// 取得件数 (最大200件. `--all-pages` の場合は全体の上限で, 200件を超えてもよい)
+    #[arg(short = 'n', long, value_parser = clap::value_parser!(i32).range(1..))]
+    limit: Option<i32>,
+
+    /// 全てのページを取得する
+    #[arg(short, long)]
+    all_pages: bool,
+
+    /// 出力形式
+    #[arg(short, long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// エンドポイントのURL
+    #[arg(long, env = "CRD_BASE_URL")]
+    base_url: Option<String>,
+}
+
+/// 出力形式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// 表形式
+    Table,
+
+    /// JSON
+    Json,
+
+    /// JSON Lines (1行に1件)
+    Jsonl,
+
+    /// APIが返却したXML
+    Xml,
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, chrono::ParseError> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").or_else(|_| NaiveDate::parse_from_str(s, "%Y%m%d"))
+}
+
+impl Args {
+    /// 引数から [`Request`] を作成する
+    fn request(&self) -> Result<Request, Box<dyn Error>> {
+        let search_type = self.search_type.unwrap_or_default();
+        let mut builder = crd_api::builder();
+        if let Some(query) = &self.query {
+            cql::parse(query)?.validate_for(search_type)?;
+            builder.query(query);
+        } else if !self.terms.is_empty() {
+            if let Some(query) = Request::new(&self.terms.join(" ")).query {
+                builder.query(query);
+            }
+        }
+        if let Some(search_type) = self.search_type {
+            builder.search_type(search_type);
+        }
+        if let Some(date) = self.crt_from {
+            builder.crt_date_from(date);
+        }
+        if let Some(date) = self.crt_to {
+            builder.crt_date_to(date);
+        }
+        if let Some(date) = self.reg_from {
+            builder.reg_date_from(date);
+        }
+        if let Some(date) = self.reg_to {
+            builder.reg_date_to(date);
+        }
+        if let Some(date) = self.lst_from {
+            builder.lst_date_from(date);
+        }
+        if let Some(date) = self.lst_to {
+            builder.lst_date_to(date);
+        }
+        if let Some(lib_id) = &self.lib_id {
+            builder.lib_id(lib_id);
+        }
+        if let Some(lib_group) = self.lib_group {
+            builder.lib_group(lib_group);
+        }
+        if let Some(sort) = self.sort {
+            builder.sort(sort);
+        }
+        if let Some(order) = self.order {
+            builder.sort_order(order);
+        }
+        if let Some(position) = self.position {
+            builder.results_get_position(position);
+        }
+        match self.limit {
+            Some(limit) if !self.all_pages && limit > MAX_RESULTS_NUM => {
+                return Err(format!(
+                    "--limit must be at most {MAX_RESULTS_NUM} without --all-pages"
+                )
+                .into());
+            }
+            Some(limit) if !self.all_pages => {
+                builder.results_num(limit);
+            }
+            _ => {}
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            let mut source = e.source();
+            while let Some(e) = source {
+                eprintln!("  caused by: {e}");
+                source = e.source();
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let request = args.request()?;
+    let mut builder = Client::builder();
+    if let Some(base_url) = &args.base_url {
+        builder = builder.base_url(base_url);
+    }
+    let client = builder.build()?;
+    let mut out = std::io::stdout().lock();
+
+    if !args.all_pages {
+        if args.format == Format::Xml {
+            let text = client.search_xml(&request).await?;
+            writeln!(out, "{text}")?;
+            return Ok(());
+        }
+        let result = client.search(&request).await?;
+        eprintln!("{} hits", result.hit_num);
+        return match args.format {
+            Format::Json => Ok(writeln!(out, "{}", serde_json::to_string_pretty(&result)?)?),
+            _ => write_items(&mut out, args.format, result.result.iter()),
+        };
+    }
+
+    let limit = args.limit.map_or(usize::MAX, |n| n as usize);
+    match args.format {
+        Format::Table | Format::Jsonl => {
+            let mut items = std::pin::pin!(client.search_all(&request).take(limit));
+            if args.format == Format::Table {
+                write_header(&mut out)?;
+            }
+            while let Some(item) = items.try_next().await? {
+                write_item(&mut out, args.format, &item)?;
+            }
+        }
+        Format::Json => {
+            let items: Vec<ResultItem> = client
+                .search_all(&request)
+                .take(limit)
+                .try_collect()
+                .await?;
+            writeln!(out, "{}", serde_json::to_string_pretty(&items)?)?;
+        }
+        Format::Xml => {
+            // 上限が1ページに収まる場合は, 上限を超えて取得しないようページの件数を絞る
+            let mut request = request;
+            if let Some(limit) = args.limit.filter(|&n| n < MAX_RESULTS_NUM) {
+                request.results_num = Some(limit);
+            }
+            // ヒット数は最初のページの値を使用する
+            let mut pages = std::pin::pin!(client.search_pages(&request));
+            let mut result: Option<ResultSet> = None;
+            while let Some(page) = pages.try_next().await? {
+                match &mut result {
+                    Some(result) => result.result.extend(page.result),
+                    None => result = Some(page),
+                }
+                if result.as_ref().is_some_and(|r| r.result.len() >= limit) {
+                    break;
+                }
+            }
+            if let Some(result) = result {
+                let mut items = result.result;
+                items.truncate(limit);
+                let result = ResultSet::new(result.hit_num, result.results_get_position, items);
+                writeln!(out, "{}", result.to_xml())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 表の列幅 (検索区分, ID, 提供館コード, 最終更新日)
+const WIDTHS: [usize; 4] = [10, 10, 7, 10];
+
+/// 表形式での表題の最大幅
+const TITLE_WIDTH: usize = 60;
+
+fn write_items<'a>(
+    out: &mut impl Write,
+    format: Format,
+    items: impl Iterator<Item = &'a ResultItem>,
+) -> Result<(), Box<dyn Error>> {
+    if format == Format::Table {
+        write_header(out)?;
+    }
+    for item in items {
+        write_item(out, format, item)?;
+    }
+    Ok(())
+}
+
+fn write_header(out: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{}",
+        row(["TYPE", "ID", "LIB-ID", "LST-DATE", "TITLE"])
+    )
+}
+
+fn write_item(
+    out: &mut impl Write,
+    format: Format,
+    item: &ResultItem,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Jsonl => writeln!(out, "{}", serde_json::to_string(item)?)?,
+        Format::Xml => writeln!(out, "{}", item.to_xml())?,
+        Format::Table | Format::Json => {
+            let search_type = item.search_type().to_string();
+            let lst_date = item.lst_date().format("%Y-%m-%d").to_string();
+            let title = truncate(item.title(), TITLE_WIDTH);
+            writeln!(
+                out,
+                "{}",
+                row([&search_type, item.id(), item.lib_id(), &lst_date, &title])
+            )?
+        }
+    }
+    Ok(())
+}
+
+/// 表の1行を作成する
+fn row(cells: [&str; 5]) -> String {
+    let mut line = String::new();
+    for (cell, width) in cells.iter().zip(WIDTHS) {
+        line.push_str(cell);
+        let pad = width.saturating_sub(cell.width()) + 2;
+        line.extend(std::iter::repeat_n(' ', pad));
+    }
+    line.push_str(cells[4]);
+    line
+}
+
+/// 表示幅が `width` を超える場合は切り詰めて `…` を付ける
+fn truncate(s: &str, width: usize) -> String {
+    let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+    if s.width() <= width {
+        return s;
+    }
+    let mut truncated = String::new();
+    let mut w = 0;
+    for c in s.chars() {
+        w += c.width().unwrap_or(0);
+        if w >= width {
+            break;
+        }
+        truncated.push(c);
+    }
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("crd").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn request_test() {
+        let request = args(&[
+            "--type",
+            "reference",
+            "--query",
+            "question any 読書",
+            "--crt-from",
+            "2020-01-01",
+            "--lib-group",
+            "public",
+            "--sort",
+            "crt-date",
+            "--order",
+            "asc",
+            "--limit",
+            "200",
+        ])
+        .request()
+        .unwrap();
+        assert_eq!(request.search_type, Some(SearchType::Reference));
+        assert_eq!(request.query.as_deref(), Some("question any 読書"));
+        assert_eq!(request.crt_date_from, NaiveDate::from_ymd_opt(2020, 1, 1));
+        assert_eq!(request.lib_group, Some(LibGroup::Public));
+        assert_eq!(request.sort, Some(SortKey::CrtDate));
+        assert_eq!(request.sort_order, Some(SortOrder::Asc));
+        assert_eq!(request.results_num, Some(MAX_RESULTS_NUM));
+
+        let request = args(&["村上", "春樹", "--all-pages", "-n", "10"])
+            .request()
+            .unwrap();
+        assert_eq!(request.query, Request::new("村上 春樹").query);
+        assert_eq!(request.results_num, None);
+
+        let request = args(&["rust", "--all-pages", "-n", "500"])
+            .request()
+            .unwrap();
+        assert_eq!(request.results_num, None);
+    }
+
+    #[test]
+    fn request_error_test() {
+        assert!(Args::try_parse_from(["crd", "--type", "foo"]).is_err());
+        assert!(Args::try_parse_from(["crd", "--crt-from", "2020/01/01"]).is_err());
+        assert!(Args::try_parse_from(["crd", "rust", "--query", "anywhere = rust"]).is_err());
+        assert!(args(&["--query", "question any"]).request().is_err());
+        assert!(args(&["rust", "--limit", "201"]).request().is_err());
+        assert!(args(&["--type", "manual", "--query", "question any 本"])
+            .request()
+            .is_err());
+        assert!(args(&[
+            "-t",
+            "collection",
+            "-q",
+            "anywhere = 地図",
+            "--sort",
+            "crt-date"
+        ])
+        .request()
+        .is_err());
+    }
+
+    #[test]
+    fn table_test() {
+        assert_eq!(
+            row(["reference", "1000000001", "2210001", "2023-03-01", "質問"]),
+            "reference   1000000001  2210001  2023-03-01  質問"
+        );
+        assert_eq!(truncate("読書感想文", 6), "読書…");
+        assert_eq!(truncate("a\nb", 6), "a b");
+    }
+}
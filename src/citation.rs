@@ -0,0 +1,620 @@
+//! 参考資料のRIS, BibTeXへの変換
+//!
+//! [`Reference`] と [`Manual`] の参考資料 ([`Bibl`]) を文献管理ソフトに取り込める形式に変換する.
+//! 書誌的事項は自由記述のため, 著者・書名・出版者・出版年はよくある書き方から推定する.
+//! 推定できない場合は書誌的事項全体を書名とする
+//!
+//! 各文献には参照元の事例の登録番号をキーワード (`crd:<sys_id>`) として,
+//! 事例のURLを備考として付ける
+//!
+//! # Example
+//!
+//! ```
+//! use crd_api::{citation::Citation, response::Bibl};
+//!
+//! let bibl = Bibl {
+//!     bibl_desc: Some("『読書感想文の書き方』 山田太郎 著 ポプラ社 2015".to_string()),
+//!     bibl_isbn: Some("4-537-25783-0".to_string()),
+//!     bibl_note: None,
+//! };
+//! let url = "https://crd.ndl.go.jp/reference/detail?page=ref_view&id=1000000001";
+//! let citation = Citation::new(&bibl, "crd1000000001_1".to_string(), "1000000001", url);
+//! assert_eq!(citation.title.as_deref(), Some("読書感想文の書き方"));
+//! assert_eq!(citation.authors, ["山田太郎"]);
+//! assert_eq!(citation.isbn.as_deref(), Some("9784537257830"));
+//! println!("{}", citation.to_ris());
+//! ```
+
+use std::fmt::Write;
+
+use crate::response::{Bibl, Manual, Reference, ResultItem};
+
+/// 責任表示の役割を表す語
+///
+/// 長いものから順に一致させる
+const ROLES: &[&str] = &[
+    "編集", "翻訳", "編著", "監修", "監訳", "原作", "著者", "著", "編", "訳", "作", "文", "絵",
+    "画", "写真",
+];
+
+/// 著者を省略したことを表す語
+const ET_AL: &[&str] = &["[ほか]", "［ほか］", "ほか", "他"];
+
+/// 参考資料の書誌情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Citation {
+    /// BibTeXのキー (`crd<sys_id>_<番号>`)
+    pub key: String,
+
+    /// 書名
+    pub title: Option<String>,
+
+    /// 著者
+    pub authors: Vec<String>,
+
+    /// 出版者
+    pub publisher: Option<String>,
+
+    /// 出版年
+    pub year: Option<String>,
+
+    /// ISBN
+    ///
+    /// 正しいISBNなら13桁の数字に正規化する
+    pub isbn: Option<String>,
+
+    /// 参考資料の備考
+    pub note: Option<String>,
+
+    /// 参照元の事例の登録番号
+    pub sys_id: String,
+
+    /// 参照元の事例のURL
+    pub source_url: String,
+}
+
+impl Citation {
+    /// 参考資料から作成する
+    pub fn new(bibl: &Bibl, key: String, sys_id: &str, source_url: &str) -> Self {
+        let desc = bibl.bibl_desc.as_deref().map(str::trim).unwrap_or_default();
+        let parsed = parse(desc);
+        Self {
+            key,
+            title: parsed.title,
+            authors: parsed.authors,
+            publisher: parsed.publisher,
+            year: parsed.year,
+            isbn: bibl
+                .bibl_isbn
+                .as_deref()
+                .map(|isbn| normalize_isbn(isbn).unwrap_or_else(|| isbn.trim().to_string())),
+            note: bibl.bibl_note.clone(),
+            sys_id: sys_id.to_string(),
+            source_url: source_url.to_string(),
+        }
+    }
+
+    /// RIS形式に変換する
+    pub fn to_ris(&self) -> String {
+        let mut ris = String::new();
+        let mut tag = |tag: &str, value: &str| {
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            writeln!(ris, "{tag}  - {value}").unwrap();
+        };
+        tag("TY", "BOOK");
+        for author in &self.authors {
+            tag("AU", author);
+        }
+        if let Some(title) = &self.title {
+            tag("TI", title);
+        }
+        if let Some(publisher) = &self.publisher {
+            tag("PB", publisher);
+        }
+        if let Some(year) = &self.year {
+            tag("PY", year);
+        }
+        if let Some(isbn) = &self.isbn {
+            tag("SN", isbn);
+        }
+        if let Some(note) = &self.note {
+            tag("N1", note);
+        }
+        tag("N1", &self.source_note());
+        tag("KW", &self.tag());
+        tag("ER", "");
+        ris
+    }
+
+    /// BibTeX形式に変換する
+    pub fn to_bibtex(&self) -> String {
+        let mut bibtex = format!("@book{{{},\n", self.key);
+        let mut field = |name: &str, value: &str| {
+            writeln!(bibtex, "  {name} = {{{}}},", escape_bibtex(value)).unwrap();
+        };
+        if !self.authors.is_empty() {
+            field("author", &self.authors.join(" and "));
+        }
+        if let Some(title) = &self.title {
+            field("title", title);
+        }
+        if let Some(publisher) = &self.publisher {
+            field("publisher", publisher);
+        }
+        if let Some(year) = &self.year {
+            field("year", year);
+        }
+        if let Some(isbn) = &self.isbn {
+            field("isbn", isbn);
+        }
+        let source = self.source_note();
+        match &self.note {
+            Some(note) => field("note", &format!("{note}. {source}")),
+            None => field("note", &source),
+        }
+        field("keywords", &self.tag());
+        bibtex.push_str("}\n");
+        bibtex
+    }
+
+    fn tag(&self) -> String {
+        format!("crd:{}", self.sys_id)
+    }
+
+    fn source_note(&self) -> String {
+        format!("レファレンス協同データベース: {}", self.source_url)
+    }
+}
+
+/// 事例の参考資料の書誌情報
+///
+/// レファレンス事例と調べ方マニュアル以外は空となる
+pub fn citations(item: &ResultItem) -> Vec<Citation> {
+    let (bibls, sys_id, url) = match item {
+        ResultItem::Reference(Reference {
+            bibl, system, url, ..
+        })
+        | ResultItem::Manual(Manual {
+            bibl, system, url, ..
+        }) => (bibl, &system.sys_id, url),
+        _ => return Vec::new(),
+    };
+    bibls
+        .iter()
+        .flatten()
+        .filter(|b| b.bibl_desc.is_some() || b.bibl_isbn.is_some())
+        .enumerate()
+        .map(|(i, b)| Citation::new(b, format!("crd{sys_id}_{}", i + 1), sys_id, url))
+        .collect()
+}
+
+/// RIS形式に変換する
+pub fn to_ris(citations: &[Citation]) -> String {
+    citations
+        .iter()
+        .map(Citation::to_ris)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// BibTeX形式に変換する
+pub fn to_bibtex(citations: &[Citation]) -> String {
+    citations
+        .iter()
+        .map(Citation::to_bibtex)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ISBNを13桁の数字に正規化する
+///
+/// 全角の数字や記号を半角に変換した上でハイフン, 空白, `ISBN` の接頭辞を取り除き,
+/// 10桁のISBNは13桁に変換する. チェックディジットが正しくない場合は [`None`] を返す
+///
+/// # Example
+///
+/// ```
+/// use crd_api::citation::normalize_isbn;
+///
+/// assert_eq!(normalize_isbn("ISBN978-4-537-25783-0").as_deref(), Some("9784537257830"));
+/// assert_eq!(normalize_isbn("4-00-000008-X").as_deref(), Some("9784000000086"));
+/// assert_eq!(normalize_isbn("978-4-537-25783-1"), None);
+/// ```
+pub fn normalize_isbn(s: &str) -> Option<String> {
+    let s: String = s.chars().map(to_halfwidth).collect();
+    let s = s.trim();
+    let s = s
+        .strip_prefix("ISBN")
+        .or_else(|| s.strip_prefix("isbn"))
+        .unwrap_or(s);
+    let chars: String = s
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' ' | ':' | '‐' | '‑' | '−' | 'ー'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    // 以降はバイト単位で扱うため, ASCII以外の文字を含む場合はここで除外する
+    if !chars.is_ascii() {
+        return None;
+    }
+    match chars.len() {
+        13 if chars.bytes().all(|b| b.is_ascii_digit()) => {
+            let digits: Vec<u32> = chars.bytes().map(|b| u32::from(b - b'0')).collect();
+            (isbn13_check(&digits[..12]) == digits[12]).then_some(chars)
+        }
+        10 if chars[..9].bytes().all(|b| b.is_ascii_digit()) => {
+            let digits: Vec<u32> = chars[..9].bytes().map(|b| u32::from(b - b'0')).collect();
+            let sum: u32 = digits.iter().zip((2..=10).rev()).map(|(d, w)| d * w).sum();
+            let check = match (11 - sum % 11) % 11 {
+                10 => 'X',
+                n => char::from_digit(n, 10)?,
+            };
+            if !chars.ends_with(check) {
+                return None;
+            }
+            let mut digits13 = vec![9, 7, 8];
+            digits13.extend(digits);
+            let check = isbn13_check(&digits13);
+            digits13.push(check);
+            Some(digits13.iter().map(|d| d.to_string()).collect())
+        }
+        _ => None,
+    }
+}
+
+/// 全角の英数字と記号 (`！` から `～`) と全角空白を半角に変換する
+fn to_halfwidth(c: char) -> char {
+    match c {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+fn isbn13_check(digits: &[u32]) -> u32 {
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/// 書誌的事項から推定した書誌情報
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Parsed {
+    title: Option<String>,
+    authors: Vec<String>,
+    publisher: Option<String>,
+    year: Option<String>,
+}
+
+/// 書誌的事項を解析する
+///
+/// 次のような書き方に対応する
+/// - `『書名』 著者 著 出版者 2015`
+/// - `著者 著. 書名. 出版者, 2010.`
+/// - `書名 / 著者 著. -- 出版者, 2010`
+fn parse(desc: &str) -> Parsed {
+    if desc.is_empty() {
+        return Parsed::default();
+    }
+    let year = find_year(desc);
+    let mut parsed = if let Some((title, rest)) = bracketed(desc) {
+        // 『書名』 著者 著 出版者 年
+        let mut authors = Vec::new();
+        let mut publisher = Vec::new();
+        let mut words = Vec::new();
+        for word in rest.split_whitespace() {
+            if year.as_deref() == Some(word.trim_matches(|c: char| !c.is_ascii_digit())) {
+                continue;
+            }
+            if is_role(word) {
+                authors.append(&mut words);
+            } else if let Some(author) = strip_role(word) {
+                words.push(author.to_string());
+                authors.append(&mut words);
+            } else {
+                words.push(word.to_string());
+            }
+        }
+        if authors.is_empty() && words.len() > 1 {
+            authors.push(words.remove(0));
+        }
+        publisher.append(&mut words);
+        Parsed {
+            title: Some(title.to_string()),
+            authors: split_authors(&authors.join(" ")),
+            publisher: non_empty(&publisher.join(" ")),
+            year: None,
+        }
+    } else if let Some((title, rest)) = desc.split_once(" / ") {
+        // 書名 / 著者 著. -- 出版者, 年
+        // 版表示などを挟む場合があるため, 出版事項は最後の区切りの後とする
+        let (responsibility, publication) = rest.split_once("--").map_or((rest, ""), |(r, _)| {
+            (r, rest.rsplit("--").next().unwrap_or_default())
+        });
+        Parsed {
+            title: non_empty(title),
+            authors: split_authors(responsibility.trim().trim_end_matches('.')),
+            publisher: publisher(publication),
+            year: None,
+        }
+    } else {
+        let parts: Vec<_> = desc
+            .split(['.', '．'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        match parts.as_slice() {
+            // 著者 著. 書名. 出版者, 年
+            [authors, title, publication, ..] if is_responsibility(authors) => Parsed {
+                title: non_empty(title),
+                authors: split_authors(authors),
+                publisher: publisher(publication),
+                year: None,
+            },
+            _ => Parsed {
+                title: Some(desc.to_string()),
+                ..Default::default()
+            },
+        }
+    };
+    parsed.year = year;
+    parsed
+}
+
+/// `『』` または `「」` で囲まれた書名と, それ以外の部分
+fn bracketed(desc: &str) -> Option<(&str, String)> {
+    for (open, close) in [('『', '』'), ('「', '」')] {
+        if let Some((before, rest)) = desc.split_once(open) {
+            if let Some((title, after)) = rest.split_once(close) {
+                return Some((title.trim(), format!("{before} {after}")));
+            }
+        }
+    }
+    None
+}
+
+/// 出版事項 (`出版者, 年`) から出版者を取り出す
+fn publisher(publication: &str) -> Option<String> {
+    let publisher = publication
+        .split([',', '，', '、'])
+        .map(str::trim)
+        .find(|s| !s.is_empty() && find_year(s).as_deref() != Some(s.trim_end_matches('.')))?;
+    non_empty(publisher.trim_end_matches('.'))
+}
+
+/// 出版年と思われる4桁の数字 (1500年から2099年) を探す
+fn find_year(s: &str) -> Option<String> {
+    let chars: Vec<char> = s.chars().collect();
+    (0..chars.len().saturating_sub(3)).rev().find_map(|i| {
+        let candidate: String = chars[i..i + 4].iter().collect();
+        let isolated = |c: Option<&char>| !c.is_some_and(char::is_ascii_digit);
+        let valid = candidate.bytes().all(|b| b.is_ascii_digit())
+            && isolated(i.checked_sub(1).and_then(|j| chars.get(j)))
+            && isolated(chars.get(i + 4))
+            && (1500..2100).contains(&candidate.parse::<u32>().ok()?);
+        valid.then_some(candidate)
+    })
+}
+
+fn is_role(word: &str) -> bool {
+    ROLES.contains(&word)
+}
+
+/// 役割を表す語で終わる場合は取り除いた部分を返す
+fn strip_role(word: &str) -> Option<&str> {
+    ROLES
+        .iter()
+        .find_map(|role| word.strip_suffix(role))
+        .filter(|s| !s.is_empty())
+}
+
+/// 責任表示 (役割を表す語で終わる) なら [`true`] を返す
+fn is_responsibility(s: &str) -> bool {
+    s.split_whitespace()
+        .last()
+        .is_some_and(|w| is_role(w) || strip_role(w).is_some())
+}
+
+/// 責任表示を著者ごとに分割する
+fn split_authors(s: &str) -> Vec<String> {
+    s.split([',', '，', '、', ';', '；'])
+        .flat_map(|s| {
+            // 役割ごとに区切られた著者 (例: `山田太郎 著 佐藤花子 訳`)
+            let mut authors = Vec::new();
+            let mut name = Vec::new();
+            for word in s.split_whitespace() {
+                if ET_AL.contains(&word) {
+                    continue;
+                }
+                if is_role(word) {
+                    authors.push(name.join(" "));
+                    name.clear();
+                } else if let Some(word) = strip_role(word) {
+                    name.push(word);
+                    authors.push(name.join(" "));
+                    name.clear();
+                } else {
+                    name.push(word);
+                }
+            }
+            authors.push(name.join(" "));
+            authors
+        })
+        .map(|s| {
+            ET_AL
+                .iter()
+                .fold(s, |s, et_al| s.trim_end_matches(et_al).trim().to_string())
+        })
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// BibTeXの特殊文字をエスケープする
+fn escape_bibtex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.split_whitespace().collect::<Vec<_>>().join(" ").chars() {
+        match c {
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Corpus;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            parse("『読書感想文の書き方』 山田太郎 著 ポプラ社 2015"),
+            Parsed {
+                title: Some("読書感想文の書き方".to_string()),
+                authors: vec!["山田太郎".to_string()],
+                publisher: Some("ポプラ社".to_string()),
+                year: Some("2015".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("栗原裕一郎 [ほか]著. 村上春樹を音楽で読み解く. 日本文芸社, 2010."),
+            Parsed {
+                title: Some("村上春樹を音楽で読み解く".to_string()),
+                authors: vec!["栗原裕一郎".to_string()],
+                publisher: Some("日本文芸社".to_string()),
+                year: Some("2010".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("江戸の地図 / 山田太郎 編, 佐藤花子 著. -- 地図出版, 1999"),
+            Parsed {
+                title: Some("江戸の地図".to_string()),
+                authors: vec!["山田太郎".to_string(), "佐藤花子".to_string()],
+                publisher: Some("地図出版".to_string()),
+                year: Some("1999".to_string()),
+            }
+        );
+        assert_eq!(
+            parse("大阪府統計書 令和3年版"),
+            Parsed {
+                title: Some("大阪府統計書 令和3年版".to_string()),
+                ..Default::default()
+            }
+        );
+        assert_eq!(find_year("ISBN 9784591145670"), None);
+    }
+
+    #[test]
+    fn isbn_test() {
+        assert_eq!(
+            normalize_isbn("978-4-537-25783-0").as_deref(),
+            Some("9784537257830")
+        );
+        assert_eq!(
+            normalize_isbn("isbn 4-537-25783-0").as_deref(),
+            Some("9784537257830")
+        );
+        assert_eq!(
+            normalize_isbn("400000008x").as_deref(),
+            Some("9784000000086")
+        );
+        assert_eq!(normalize_isbn("4-537-25783-5"), None);
+        assert_eq!(normalize_isbn("978-4-591-14567-0"), None);
+        assert_eq!(normalize_isbn("12345"), None);
+    }
+
+    #[test]
+    fn isbn_non_ascii_test() {
+        assert_eq!(normalize_isbn("1234567あ"), None);
+        assert_eq!(normalize_isbn("123456789あ"), None);
+        assert_eq!(normalize_isbn("ISBN978-4-537-2578３-あ"), None);
+    }
+
+    #[test]
+    fn isbn_fullwidth_test() {
+        assert_eq!(
+            normalize_isbn("ISBN４５３７２５７８３０").as_deref(),
+            Some("9784537257830")
+        );
+        assert_eq!(
+            normalize_isbn("ＩＳＢＮ９７８－４－５３７－２５７８３－０").as_deref(),
+            Some("9784537257830")
+        );
+        assert_eq!(
+            normalize_isbn("４ー００ー０００００８ーｘ").as_deref(),
+            Some("9784000000086")
+        );
+    }
+
+    #[test]
+    fn ris_test() {
+        let corpus = Corpus::fixtures();
+        let citations = citations(&corpus.items()[0]);
+        assert_eq!(citations.len(), 2);
+        let expected = [
+            "TY  - BOOK",
+            "AU  - 山田太郎",
+            "TI  - 読書感想文の書き方",
+            "PB  - ポプラ社",
+            "PY  - 2015",
+            // チェックディジットが正しくないISBNはそのまま
+            "SN  - 978-4-591-14567-0",
+            "N1  - 当館請求記号 019/ヤ",
+            "N1  - レファレンス協同データベース: https://crd.ndl.go.jp/reference/detail?page=ref_view&id=1000000001",
+            "KW  - crd:1000000001",
+            "ER  - ",
+        ];
+        assert_eq!(citations[0].to_ris().lines().collect::<Vec<_>>(), expected);
+        assert!(to_ris(&citations).contains("ER  - \n\nTY  - BOOK\n"));
+        // 特別コレクションは参考資料を持たない
+        assert!(super::citations(&corpus.items()[6]).is_empty());
+    }
+
+    #[test]
+    fn bibtex_test() {
+        let corpus = Corpus::fixtures();
+        let citations = citations(&corpus.items()[1]);
+        assert_eq!(
+            to_bibtex(&citations),
+            "@book{crd1000000002_1,
+  author = {栗原裕一郎},
+  title = {村上春樹を音楽で読み解く},
+  publisher = {日本文芸社},
+  year = {2010},
+  isbn = {9784537257830},
+  note = {レファレンス協同データベース: https://crd.ndl.go.jp/reference/detail?page=ref\\_view\\&id=1000000002},
+  keywords = {crd:1000000002},
+}
+"
+        );
+        let citations = super::citations(&corpus.items()[3]);
+        assert_eq!(
+            citations[0].authors,
+            ["Jim Blandy", "Jason Orendorff", "Leonora F.S. Tindall"]
+        );
+        assert_eq!(
+            citations[0].publisher.as_deref(),
+            Some("オライリー・ジャパン")
+        );
+        assert_eq!(citations[0].isbn.as_deref(), Some("9784873119786"));
+
+        let citations = super::citations(&corpus.items()[4]);
+        assert_eq!(citations[0].authors, ["国立国会図書館"]);
+        assert_eq!(citations[0].publisher, None);
+        assert_eq!(citations[0].year.as_deref(), Some("2020"));
+
+        assert_eq!(escape_bibtex("100% {R&D}"), r"100\% \{R\&D\}");
+    }
+}
@@ -1,25 +1,86 @@
+use std::io::Cursor;
+
+use futures::{stream, Stream, TryStreamExt};
+use reqwest::StatusCode;
+use tokio::io::AsyncBufRead;
+use tokio_util::{either::Either, io::StreamReader};
+
+#[macro_use]
+mod config;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod cassette;
+mod rate_limit;
+mod retry;
+
+pub use cassette::{Cassette, CassetteError, CassetteMode};
+use config::Config;
+use rate_limit::PermitReader;
+pub use rate_limit::{InvalidRateLimitError, RateLimit, RateLimitPermit, RateLimiter};
+use retry::retry_delay;
+pub use retry::RetryPolicy;
+
 use crate::{
     error::{ApiErrors, Error},
-    request::Request,
-    response::ResultSet,
+    request::{Request, MAX_RESULTS_NUM},
+    response::{AsyncResultReader, ResultItem, ResultSet},
 };
 
+/// デフォルトのUser-Agent
+pub const DEFAULT_USER_AGENT: &str = "crd-api-rs";
+
+/// APIクライアント
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use crd_api::client::Client;
+///
+/// fn main() -> anyhow::Result<()> {
+///     // ローカルのモックサーバーに接続するクライアントを作成
+///     let client = Client::builder()
+///         .base_url("http://127.0.0.1:8080/api/refsearch")
+///         .timeout(Duration::from_secs(10))
+///         .build()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
 pub struct Client {
     pub client: reqwest::Client,
+
+    /// エンドポイントのURL
+    pub base_url: String,
+
+    /// 再試行の設定
+    ///
+    /// [`None`] の場合は再試行しない
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// リクエスト頻度の制限
+    ///
+    /// クライアントを複製した場合は複製元と制限を共有する.
+    /// [`None`] の場合は制限しない
+    pub rate_limiter: Option<RateLimiter>,
+
+    /// レスポンスの記録・再生
+    ///
+    /// [`None`] の場合は記録・再生しない
+    pub cassette: Option<Cassette>,
 }
 
 impl Client {
-    pub fn new() -> Result<Self, reqwest::Error> {
-        let headers = reqwest::header::HeaderMap::from_iter([(
-            reqwest::header::HOST,
-            "crd.ndl.go.jp".parse().unwrap(),
-        )]);
-        Ok(Client {
-            client: reqwest::Client::builder()
-                .default_headers(headers)
-                .user_agent("crd-api-rs")
-                .build()?,
-        })
+    /// デフォルト設定のクライアントを作成する
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// クライアントのビルダーを作成する
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
     }
 
     /// リクエストを行って検索結果を取得する
@@ -31,16 +92,680 @@ impl Client {
     /// - リクエストに失敗したとき
     /// - 返却されたXMLの解析に失敗したとき
     /// - APIがエラーを返したとき
+    ///
+    /// [`retry_policy`](Self::retry_policy) が設定されている場合は再試行を行い,
+    /// 2回以上試行して失敗したときは [`Error::Retry`] を返す
     pub async fn search(&self, request: &Request) -> Result<ResultSet, Error> {
-        let url = request.url();
-        let resp = self.client.get(&url).send().await?.text().await?;
-        let res = ResultSet::from_xml(&resp);
-        if res.is_err() {
-            let res = ApiErrors::from_xml(&resp);
-            if let Ok(e) = res {
-                return Err(e.into());
+        self.search_with_text(request)
+            .await
+            .map(|(result, _)| result)
+    }
+
+    /// リクエストを行ってAPIが返却したXMLを取得する
+    ///
+    /// [`search`](Self::search) と同様に再試行, リクエスト頻度の制限, レスポンスの記録・再生を行い,
+    /// 返却されたXMLを解析できた場合のみ返す
+    ///
+    /// # Errors
+    ///
+    /// [`search`](Self::search) と同様
+    pub async fn search_xml(&self, request: &Request) -> Result<String, Error> {
+        self.search_with_text(request).await.map(|(_, text)| text)
+    }
+
+    async fn search_with_text(&self, request: &Request) -> Result<(ResultSet, String), Error> {
+        let mut attempt = 1;
+        loop {
+            match self.search_once(request).await {
+                Ok(res) => return Ok(res),
+                Err(e) => {
+                    let delay = retry_delay(self.retry_policy.as_ref(), attempt, e)?;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn search_once(&self, request: &Request) -> Result<(ResultSet, String), Error> {
+        let (status, text) = match &self.cassette {
+            Some(cassette) if cassette.mode() == CassetteMode::Replay => {
+                (StatusCode::OK, cassette.load(request)?)
             }
+            _ => self.fetch(request).await?,
+        };
+        let result = parse_response(status, &text)?;
+        Ok((result, text))
+    }
+
+    /// リクエストを行ってレスポンスを読み込む
+    ///
+    /// カセットが記録モードの場合, 成功したレスポンスを保存する
+    async fn fetch(&self, request: &Request) -> Result<(StatusCode, String), Error> {
+        let url = request.url_with_base(&self.base_url);
+        let _permit = match &self.rate_limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
+        };
+        let resp = self.client.get(&url).send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if let Some(cassette) = &self.cassette {
+            if cassette.mode() == CassetteMode::Record && status.is_success() {
+                cassette.save(request, &text)?;
+            }
+        }
+        Ok((status, text))
+    }
+
+    /// リクエストを行って検索結果を1件ずつ読み込むリーダーを取得する
+    ///
+    /// レスポンス全体をメモリに読み込まずに解析するため, 返却件数が多い場合に使用する.
+    /// ヘッダーは [`AsyncResultReader::header`] で取得できる.
+    /// [`retry_policy`](Self::retry_policy) による再試行は行わない.
+    /// [`rate_limiter`](Self::rate_limiter) の同時に送信できるリクエスト数はリーダーを破棄するまで消費する.
+    /// [`cassette`](Self::cassette) を使用する場合はレスポンス全体を読み込む
+    ///
+    /// # Errors
+    ///
+    /// [`search`](Self::search) と同様. 各結果の解析エラーはリーダーから返す
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use crd_api::client::Client;
+    /// use crd_api::request::Request;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let client = Client::new()?;
+    ///     let mut reader = client.search_stream(&Request::new("読書")).await?;
+    ///     println!("{}", reader.header().hit_num);
+    ///     while let Some(item) = reader.next_item().await {
+    ///         println!("{:?}", item?);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn search_stream(
+        &self,
+        request: &Request,
+    ) -> Result<AsyncResultReader<impl AsyncBufRead + Unpin>, Error> {
+        if let Some(cassette) = &self.cassette {
+            let text = match cassette.mode() {
+                CassetteMode::Replay => cassette.load(request)?,
+                CassetteMode::Record => match self.fetch(request).await? {
+                    (status, text) if status.is_success() => text,
+                    (status, text) => return Err(error_response(status, &text)),
+                },
+            };
+            let reader = Either::Right(Cursor::new(text.into_bytes()));
+            return AsyncResultReader::new(reader).await;
+        }
+        let url = request.url_with_base(&self.base_url);
+        let permit = match &self.rate_limiter {
+            Some(limiter) => Some(limiter.acquire().await),
+            None => None,
+        };
+        let resp = self.client.get(&url).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await?;
+            return Err(error_response(status, &text));
+        }
+        // レスポンスを読み終えるまで同時に送信できるリクエスト数を消費する
+        let stream = resp.bytes_stream().map_err(std::io::Error::other);
+        let reader = PermitReader::new(StreamReader::new(stream), permit);
+        AsyncResultReader::new(Either::Left(reader)).await
+    }
+
+    /// 全ての検索結果を順に返すストリームを作成する
+    ///
+    /// [`results_get_position`](Request::results_get_position) から最後の結果まで,
+    /// [`results_num`](Request::results_num) 件 (最大 [`MAX_RESULTS_NUM`] 件) ずつ必要に応じてリクエストを行う
+    ///
+    /// # Errors
+    ///
+    /// リクエストでエラーが発生した場合はそのエラーを返して終了する
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use crd_api::{client::Client, cql::Query};
+    /// use futures::TryStreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let client = Client::new()?;
+    ///     let request = crd_api::builder()
+    ///         .query(Query::any("question", &["読書"]).to_string())
+    ///         .build()?;
+    ///     let items: Vec<_> = client.search_all(&request).try_collect().await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn search_all<'a>(
+        &'a self,
+        request: &Request,
+    ) -> impl Stream<Item = Result<ResultItem, Error>> + 'a {
+        self.search_pages(request)
+            .map_ok(|result| stream::iter(result.result.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// 全ての検索結果をページごとに返すストリームを作成する
+    ///
+    /// [`search_all`](Self::search_all) と同様にリクエストを行い, 各レスポンスの [`ResultSet`] を返す.
+    /// [`hit_num`](ResultSet::hit_num) などのヘッダーが必要な場合に使用する
+    ///
+    /// # Errors
+    ///
+    /// リクエストでエラーが発生した場合はそのエラーを返して終了する
+    pub fn search_pages<'a>(
+        &'a self,
+        request: &Request,
+    ) -> impl Stream<Item = Result<ResultSet, Error>> + 'a {
+        let (request, position) = first_page(request);
+        stream::try_unfold(Some((request, position)), move |state| async move {
+            let Some((mut request, position)) = state else {
+                return Ok::<_, Error>(None);
+            };
+            request.results_get_position = Some(position);
+            let result = self.search(&request).await?;
+            let state = next_position(position, &result).map(|next| (request, next));
+            Ok(Some((result, state)))
+        })
+    }
+}
+
+/// 全件取得の最初のリクエストと取得位置
+pub(crate) fn first_page(request: &Request) -> (Request, i32) {
+    let mut request = request.clone();
+    request.results_num = Some(
+        request
+            .results_num
+            .map_or(MAX_RESULTS_NUM, |n| n.clamp(1, MAX_RESULTS_NUM)),
+    );
+    let position = request.results_get_position.unwrap_or(1).max(1);
+    (request, position)
+}
+
+/// 全件取得の次の取得位置
+///
+/// 最後の結果まで取得済みなら [`None`]
+pub(crate) fn next_position(position: i32, result: &ResultSet) -> Option<i32> {
+    let next = position + result.len() as i32;
+    (!result.is_empty() && next <= result.hit_num as i32).then_some(next)
+}
+
+/// 成功以外のHTTPステータスのレスポンスをエラーに変換する
+pub(crate) fn error_response(status: StatusCode, text: &str) -> Error {
+    match ApiErrors::from_xml(text) {
+        Ok(e) => e.into(),
+        Err(_) => Error::Status(status),
+    }
+}
+
+/// レスポンスを [`ResultSet`] に変換する
+pub(crate) fn parse_response(status: StatusCode, text: &str) -> Result<ResultSet, Error> {
+    let res = ResultSet::from_xml(text);
+    if res.is_err() {
+        if let Ok(e) = ApiErrors::from_xml(text) {
+            return Err(e.into());
+        }
+        if !status.is_success() {
+            return Err(Error::Status(status));
+        }
+    }
+    res.map_err(Error::De)
+}
+
+/// `Host` ヘッダーの指定方法
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HostHeader {
+    /// エンドポイントのURLから決定する (デフォルト)
+    #[default]
+    FromUrl,
+
+    /// 指定した値を常に送信する
+    Fixed(reqwest::header::HeaderValue),
+}
+
+/// [`Client`] のビルダー
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    config: Config,
+    client: Option<reqwest::Client>,
+    cassette: Option<Cassette>,
+}
+
+impl ClientBuilder {
+    /// ビルダーを作成する
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    config_setters!();
+
+    /// 構築済みの [`reqwest::Client`] を使用する
+    ///
+    /// 指定した場合, `host_header`, `user_agent`, `timeout`, `connect_timeout` は無視される
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// レスポンスの記録・再生 (デフォルト: 記録・再生しない)
+    pub fn cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// [`Client`] を作成する
+    ///
+    /// # Errors
+    ///
+    /// [`reqwest::Client`] の作成に失敗したとき,
+    /// または [`rate_limit`](Self::rate_limit) の設定が正しくないときエラーを返す
+    pub fn build(self) -> Result<Client, Error> {
+        let rate_limiter = self.config.rate_limiter()?;
+        let client = match self.client {
+            Some(client) => client,
+            None => build_http_client!(reqwest::Client::builder(), &self.config)?,
+        };
+        Ok(Client {
+            client,
+            base_url: self.config.endpoint(),
+            retry_policy: self.config.retry_policy,
+            rate_limiter,
+            cassette: self.cassette,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        mock::{Corpus, MockServer},
+        request::DEFAULT_ENDPOINT,
+    };
+
+    /// `sys-id` のみが異なるレファレンス事例のXML
+    pub(crate) fn reference_xml(sys_id: u32) -> String {
+        format!(
+            "<reference>
+            <question>質問{sys_id}</question>
+            <reg-id>{sys_id}</reg-id>
+            <answer>回答{sys_id}</answer>
+            <crt-date>20230101</crt-date>
+            <keyword>rust</keyword>
+            <system>
+                <reg-date>20230101000000</reg-date>
+                <lst-date>20230101000000</lst-date>
+                <sys-id>{sys_id}</sys-id>
+                <lib-id>0000000</lib-id>
+                <lib-name>図書館</lib-name>
+                <file-num>0</file-num>
+            </system>
+            <url>https://crd.ndl.go.jp/reference/detail?page=ref_view&amp;id={sys_id}</url>
+            </reference>"
+        )
+    }
+
+    /// `hit_num` 件の結果のうち, クエリで指定された範囲を返すレスポンス
+    pub(crate) fn page_xml(target: &str, hit_num: u32) -> String {
+        let param = |name: &str| {
+            target
+                .split(['?', '&'])
+                .find_map(|p| p.strip_prefix(name)?.strip_prefix('='))
+                .and_then(|v| v.parse::<u32>().ok())
+        };
+        let position = param("results_get_position").unwrap_or(1);
+        let num = param("results_num").unwrap_or(200);
+        let items: String = (position..(position + num).min(hit_num + 1))
+            .map(|i| format!("<result>{}</result>", reference_xml(i)))
+            .collect();
+        format!(
+            "<result_set>
+            <hit_num>{hit_num}</hit_num>
+            <results_get_position>{position}</results_get_position>
+            <results_num>{}</results_num>
+            <results_cd>0</results_cd>
+            {items}
+            </result_set>",
+            (hit_num + 1).saturating_sub(position).min(num)
+        )
+    }
+
+    /// `sys-id` が `1` から `n` のレファレンス事例を検索対象とするモックサーバーを起動する
+    pub(crate) async fn references_server(n: u32) -> MockServer {
+        let corpus = Corpus::from_xml(&page_xml(&format!("/?results_num={n}"), n)).unwrap();
+        MockServer::start(corpus).await.unwrap()
+    }
+
+    fn retry_client(server: &MockServer, max_attempts: u32) -> Client {
+        Client::builder()
+            .base_url(server.url())
+            .retry_policy(RetryPolicy {
+                max_attempts,
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn rate_limited_client(server: &MockServer, rate_limit: RateLimit) -> Client {
+        Client::builder()
+            .base_url(server.url())
+            .rate_limit(rate_limit)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn base_url_test() {
+        let server = references_server(0).await;
+        let client = Client::builder().base_url(server.url()).build().unwrap();
+        let result = client.search(&Request::new("rust")).await.unwrap();
+        assert_eq!(result.hit_num, 0);
+        assert!(result.is_empty());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("/api/refsearch?query="));
+    }
+
+    #[tokio::test]
+    async fn api_error_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = Client::builder()
+            .base_url(server.url())
+            .host_header(HostHeader::Fixed(
+                reqwest::header::HeaderValue::from_static("crd.ndl.go.jp"),
+            ))
+            .user_agent("crd-api-rs-test")
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let err = client.search(&Request::default()).await.unwrap_err();
+        assert!(matches!(err, Error::Api(_)));
+    }
+
+    #[tokio::test]
+    async fn search_all_test() {
+        let server = references_server(5).await;
+        let client = server.client().unwrap();
+        let request = crate::builder()
+            .query("anywhere = rust")
+            .results_num(2)
+            .build()
+            .unwrap();
+        let items: Vec<ResultItem> = client.search_all(&request).try_collect().await.unwrap();
+        let ids: Vec<&str> = items.iter().map(ResultItem::id).collect();
+        assert_eq!(ids, ["1", "2", "3", "4", "5"]);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn search_all_max_results_num_test() {
+        let server = references_server(201).await;
+        let client = server.client().unwrap();
+        let request = crate::builder()
+            .query("anywhere = rust")
+            .results_num(1000)
+            .build()
+            .unwrap();
+        let items: Vec<ResultItem> = client.search_all(&request).try_collect().await.unwrap();
+        assert_eq!(items.len(), 201);
+        for target in server.requests() {
+            assert!(target.contains("results_num=200"), "{target}");
+        }
+    }
+
+    #[tokio::test]
+    async fn search_all_empty_test() {
+        let server = references_server(0).await;
+        let client = server.client().unwrap();
+        let items: Vec<ResultItem> = client
+            .search_all(&Request::new("rust"))
+            .try_collect()
+            .await
+            .unwrap();
+        assert!(items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_pages_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = server.client().unwrap();
+        let request = crate::builder()
+            .query("anywhere any 図書館 地図")
+            .results_num(1)
+            .build()
+            .unwrap();
+        let pages: Vec<ResultSet> = client.search_pages(&request).try_collect().await.unwrap();
+        assert!(pages.len() > 1);
+        for (i, page) in pages.iter().enumerate() {
+            assert_eq!(page.hit_num as usize, pages.len());
+            assert_eq!(page.results_get_position as usize, i + 1);
+            assert_eq!(page.len(), 1);
         }
-        res.map_err(Error::De)
+    }
+
+    #[tokio::test]
+    async fn search_xml_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = retry_client(&server, 2);
+        let request = Request::new("地図");
+        let text = client.search_xml(&request).await.unwrap();
+        assert_eq!(
+            ResultSet::from_xml(&text).unwrap(),
+            client.search(&request).await.unwrap()
+        );
+        let invalid = crate::builder().query("question any").build().unwrap();
+        assert!(matches!(
+            client.search_xml(&invalid).await,
+            Err(Error::Api(_))
+        ));
+
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 2);
+        let err = client.search_xml(&request).await.unwrap_err();
+        assert_eq!(err.attempts(), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_test() {
+        let server = references_server(0).await;
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 2);
+        let client = retry_client(&server, 3);
+        client.search(&Request::new("rust")).await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_exhausted_test() {
+        let server = references_server(0).await;
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 2);
+        let client = retry_client(&server, 2);
+        let err = client.search(&Request::new("rust")).await.unwrap_err();
+        assert_eq!(err.attempts(), 2);
+        assert!(matches!(
+            err.last_error(),
+            Error::Status(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+        assert_eq!(err.to_string(), "failed after 2 attempts");
+        assert_eq!(
+            std::error::Error::source(&err).unwrap().to_string(),
+            "unexpected HTTP status: 503 Service Unavailable"
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_not_retryable_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = retry_client(&server, 3);
+        let err = client.search(&Request::default()).await.unwrap_err();
+        assert!(matches!(err, Error::Api(_)));
+        assert_eq!(err.attempts(), 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_test() {
+        let server = references_server(0).await;
+        let client = rate_limited_client(
+            &server,
+            RateLimit {
+                requests_per_second: 20.0,
+                burst: 1,
+                max_concurrency: None,
+            },
+        );
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.search(&Request::new("rust")).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        // 1件は即時, 残り3件は50msごと
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn max_concurrency_test() {
+        let server = references_server(0).await;
+        server.set_latency(Duration::from_millis(30));
+        let client = rate_limited_client(
+            &server,
+            RateLimit {
+                requests_per_second: f64::INFINITY,
+                burst: 1,
+                max_concurrency: Some(2),
+            },
+        );
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..6)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.search(&Request::new("rust")).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        // 2件ずつ3回に分けて送信される
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn invalid_rate_limit_test() {
+        let rate_limit = RateLimit {
+            requests_per_second: 0.0,
+            ..Default::default()
+        };
+        let err = Client::builder()
+            .rate_limit(rate_limit.clone())
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::RateLimit(_)));
+
+        // 後から指定した制限が優先される
+        let limiter = RateLimiter::new(RateLimit::default());
+        let client = Client::builder()
+            .rate_limit(rate_limit)
+            .rate_limiter(limiter)
+            .build()
+            .unwrap();
+        assert!(client.rate_limiter.is_some());
+    }
+
+    #[tokio::test]
+    async fn search_stream_permit_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = rate_limited_client(
+            &server,
+            RateLimit {
+                requests_per_second: 1000.0,
+                burst: 10,
+                max_concurrency: Some(1),
+            },
+        );
+        let request = Request::new("地図");
+        let reader = client.search_stream(&request).await.unwrap();
+        // リーダーを破棄するまで次のリクエストは送信されない
+        let pending = tokio::time::timeout(Duration::from_millis(50), client.search(&request));
+        assert!(pending.await.is_err());
+        drop(reader);
+        client.search(&request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_stream_test() {
+        let server = references_server(3).await;
+        let client = server.client().unwrap();
+        let reader = client.search_stream(&Request::new("rust")).await.unwrap();
+        assert_eq!(reader.header().hit_num, 3);
+        let items: Vec<ResultItem> = reader.into_stream().try_collect().await.unwrap();
+        assert_eq!(items.len(), 3);
+
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+        let Err(err) = client.search_stream(&Request::new("rust")).await else {
+            panic!("expected error");
+        };
+        assert!(matches!(
+            err,
+            Error::Status(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+    }
+
+    #[tokio::test]
+    async fn cassette_test() {
+        let dir = cassette::tests::temp_dir();
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = Client::builder()
+            .base_url(server.url())
+            .cassette(Cassette::record(&dir))
+            .build()
+            .unwrap();
+        let request = Request::new("地図");
+        let recorded = client.search(&request).await.unwrap();
+        let invalid = crate::builder().query("question any").build().unwrap();
+        assert!(matches!(client.search(&invalid).await, Err(Error::Api(_))));
+        let stream_request = Request::new("rust");
+        client.search_stream(&stream_request).await.unwrap();
+        drop(server);
+
+        let client = Client::builder()
+            .base_url("http://127.0.0.1:9/api/refsearch")
+            .cassette(Cassette::replay(&dir))
+            .build()
+            .unwrap();
+        assert_eq!(client.search(&request).await.unwrap(), recorded);
+        assert!(matches!(client.search(&invalid).await, Err(Error::Api(_))));
+        let reader = client.search_stream(&stream_request).await.unwrap();
+        let items: Vec<ResultItem> = reader.into_stream().try_collect().await.unwrap();
+        assert_eq!(items.len(), 1);
+        let err = client.search(&Request::new("音楽")).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Cassette(CassetteError::NotRecorded(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn default_base_url_test() {
+        let client = Client::new().unwrap();
+        assert_eq!(client.base_url, DEFAULT_ENDPOINT);
     }
 }
@@ -0,0 +1,298 @@
+//! 同期的にリクエストを行うクライアント
+//!
+//! `blocking` featureが必要
+//!
+//! # Example
+//!
+//! ```no_run
+//! use crd_api::{client::blocking::Client, cql::Query, request::SearchType};
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let client = Client::new()?;
+//!     let request = crd_api::builder()
+//!         .search_type(SearchType::Reference)
+//!         .query(Query::any("question", &["読書"]).to_string())
+//!         .build()?;
+//!     let result = client.search(&request)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::{BufRead, BufReader};
+
+use crate::{
+    error::Error,
+    request::Request,
+    response::{ResultItem, ResultReader, ResultSet},
+};
+
+use super::{
+    config::Config, error_response, first_page, next_position, parse_response,
+    rate_limit::PermitReader, retry::retry_delay, RateLimiter, RetryPolicy,
+};
+
+/// 同期的にリクエストを行うAPIクライアント
+///
+/// [`Client`](super::Client) と同じ設定を使用できる.
+/// 非同期ランタイムの中では使用できない
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub client: reqwest::blocking::Client,
+
+    /// エンドポイントのURL
+    pub base_url: String,
+
+    /// 再試行の設定
+    ///
+    /// [`None`] の場合は再試行しない
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// リクエスト頻度の制限
+    ///
+    /// クライアントを複製した場合は複製元と制限を共有する.
+    /// [`None`] の場合は制限しない
+    pub rate_limiter: Option<RateLimiter>,
+}
+
+impl Client {
+    /// デフォルト設定のクライアントを作成する
+    pub fn new() -> Result<Self, Error> {
+        Self::builder().build()
+    }
+
+    /// クライアントのビルダーを作成する
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// リクエストを行って検索結果を取得する
+    ///
+    /// # Errors
+    ///
+    /// 以下の場合エラーを返す
+    ///
+    /// - リクエストに失敗したとき
+    /// - 返却されたXMLの解析に失敗したとき
+    /// - APIがエラーを返したとき
+    ///
+    /// [`retry_policy`](Self::retry_policy) が設定されている場合は再試行を行い,
+    /// 2回以上試行して失敗したときは [`Error::Retry`] を返す
+    pub fn search(&self, request: &Request) -> Result<ResultSet, Error> {
+        let mut attempt = 1;
+        loop {
+            match self.search_once(request) {
+                Ok(res) => return Ok(res),
+                Err(e) => {
+                    let delay = retry_delay(self.retry_policy.as_ref(), attempt, e)?;
+                    std::thread::sleep(delay);
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    fn search_once(&self, request: &Request) -> Result<ResultSet, Error> {
+        let url = request.url_with_base(&self.base_url);
+        let _permit = self.rate_limiter.as_ref().map(|l| l.acquire_blocking());
+        let resp = self.client.get(&url).send()?;
+        let status = resp.status();
+        let text = resp.text()?;
+        parse_response(status, &text)
+    }
+
+    /// リクエストを行って検索結果を1件ずつ読み込むリーダーを取得する
+    ///
+    /// [`Client::search_stream`](super::Client::search_stream) の同期版
+    ///
+    /// # Errors
+    ///
+    /// [`search`](Self::search) と同様. 各結果の解析エラーはリーダーから返す
+    pub fn search_stream(&self, request: &Request) -> Result<ResultReader<impl BufRead>, Error> {
+        let url = request.url_with_base(&self.base_url);
+        let permit = self.rate_limiter.as_ref().map(|l| l.acquire_blocking());
+        let resp = self.client.get(&url).send()?;
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text()?;
+            return Err(error_response(status, &text));
+        }
+        // レスポンスを読み終えるまで同時に送信できるリクエスト数を消費する
+        ResultReader::new(PermitReader::new(BufReader::new(resp), permit))
+    }
+
+    /// 全ての検索結果を順に返すイテレータを作成する
+    ///
+    /// [`Client::search_all`](super::Client::search_all) と同様に必要に応じてリクエストを行う
+    ///
+    /// # Errors
+    ///
+    /// リクエストでエラーが発生した場合はそのエラーを返して終了する
+    pub fn search_all<'a>(
+        &'a self,
+        request: &Request,
+    ) -> impl Iterator<Item = Result<ResultItem, Error>> + 'a {
+        let (request, position) = first_page(request);
+        let mut state = Some((request, position));
+        let mut items = Vec::new().into_iter();
+        std::iter::from_fn(move || loop {
+            if let Some(item) = items.next() {
+                return Some(Ok(item));
+            }
+            let (mut request, position) = state.take()?;
+            request.results_get_position = Some(position);
+            match self.search(&request) {
+                Ok(result) => {
+                    state = next_position(position, &result).map(|next| (request, next));
+                    items = result.result.into_iter();
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        })
+    }
+}
+
+/// [`Client`] のビルダー
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    config: Config,
+    client: Option<reqwest::blocking::Client>,
+}
+
+impl ClientBuilder {
+    /// ビルダーを作成する
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    config_setters!();
+
+    /// 構築済みの [`reqwest::blocking::Client`] を使用する
+    ///
+    /// 指定した場合, `host_header`, `user_agent`, `timeout`, `connect_timeout` は無視される
+    pub fn client(mut self, client: reqwest::blocking::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// [`Client`] を作成する
+    ///
+    /// # Errors
+    ///
+    /// [`reqwest::blocking::Client`] の作成に失敗したとき,
+    /// または [`rate_limit`](Self::rate_limit) の設定が正しくないときエラーを返す
+    pub fn build(self) -> Result<Client, Error> {
+        let rate_limiter = self.config.rate_limiter()?;
+        let client = match self.client {
+            Some(client) => client,
+            None => build_http_client!(reqwest::blocking::Client::builder(), &self.config)?,
+        };
+        Ok(Client {
+            client,
+            base_url: self.config.endpoint(),
+            retry_policy: self.config.retry_policy,
+            rate_limiter,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::client::{tests::references_server, RateLimit};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_test() {
+        let server = references_server(0).await;
+        let url = server.url();
+        tokio::task::spawn_blocking(move || {
+            let client = Client::builder().base_url(url).build().unwrap();
+            let result = client.search(&Request::new("rust")).unwrap();
+            assert!(result.is_empty());
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_all_test() {
+        let server = references_server(7).await;
+        let url = server.url();
+        tokio::task::spawn_blocking(move || {
+            let client = Client::builder()
+                .base_url(url)
+                .rate_limit(RateLimit {
+                    requests_per_second: 100.0,
+                    burst: 1,
+                    max_concurrency: Some(1),
+                })
+                .build()
+                .unwrap();
+            let request = crate::builder()
+                .query("anywhere = rust")
+                .results_num(3)
+                .build()
+                .unwrap();
+            let items: Vec<ResultItem> = client
+                .search_all(&request)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(items.len(), 7);
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn search_stream_test() {
+        let server = references_server(4).await;
+        let url = server.url();
+        tokio::task::spawn_blocking(move || {
+            let client = Client::builder().base_url(url).build().unwrap();
+            let reader = client.search_stream(&Request::new("rust")).unwrap();
+            assert_eq!(reader.header().hit_num, 4);
+            assert_eq!(reader.count(), 4);
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn retry_test() {
+        let server = references_server(0).await;
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 3);
+        let url = server.url();
+        tokio::task::spawn_blocking(move || {
+            let client = Client::builder()
+                .base_url(url)
+                .retry_policy(RetryPolicy {
+                    max_attempts: 3,
+                    base_delay: Duration::from_millis(1),
+                    ..Default::default()
+                })
+                .build()
+                .unwrap();
+            let err = client.search(&Request::new("rust")).unwrap_err();
+            assert_eq!(err.attempts(), 3);
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn invalid_rate_limit_test() {
+        let err = Client::builder()
+            .rate_limit(RateLimit {
+                requests_per_second: f64::NAN,
+                ..Default::default()
+            })
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::RateLimit(_)));
+    }
+}
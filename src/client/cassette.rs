@@ -0,0 +1,181 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::request::Request;
+
+/// カセットの動作モード
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// 実際にリクエストを行い, 成功したレスポンスを保存する
+    Record,
+
+    /// 保存したレスポンスを返し, リクエストは行わない
+    Replay,
+}
+
+/// レスポンスを記録・再生するカセット
+///
+/// リクエストごとに [`Request::url`] を `<hash>.url`, 返却されたXMLをそのまま `<hash>.xml`
+/// としてディレクトリに保存する. `<hash>` はURLから求めた値で, 実行環境によらず一定となる.
+/// URLはエンドポイントに関わらず [`DEFAULT_ENDPOINT`](crate::request::DEFAULT_ENDPOINT)
+/// に対するものを使用する
+///
+/// # Example
+///
+/// ```no_run
+/// use crd_api::{
+///     client::{Cassette, Client},
+///     request::Request,
+/// };
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     // 実際のAPIのレスポンスを記録する
+///     let client = Client::builder()
+///         .cassette(Cassette::record("tests/cassettes"))
+///         .build()?;
+///     let recorded = client.search(&Request::new("読書")).await?;
+///
+///     // 記録したレスポンスを再生する
+///     let client = Client::builder()
+///         .cassette(Cassette::replay("tests/cassettes"))
+///         .build()?;
+///     let replayed = client.search(&Request::new("読書")).await?;
+///     assert_eq!(recorded, replayed);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cassette {
+    dir: PathBuf,
+    mode: CassetteMode,
+}
+
+impl Cassette {
+    /// 指定したディレクトリとモードのカセットを作成する
+    pub fn new(dir: impl Into<PathBuf>, mode: CassetteMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    /// 記録モードのカセットを作成する
+    ///
+    /// ディレクトリが存在しない場合は最初の記録時に作成する
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self::new(dir, CassetteMode::Record)
+    }
+
+    /// 再生モードのカセットを作成する
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self::new(dir, CassetteMode::Replay)
+    }
+
+    /// 保存先のディレクトリ
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 動作モード
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// リクエストに対するレスポンスを保存するファイルのパス
+    pub fn path(&self, request: &Request) -> PathBuf {
+        self.dir.join(format!("{}.xml", key(&request.url())))
+    }
+
+    /// 保存したレスポンスを読み込む
+    ///
+    /// # Errors
+    ///
+    /// リクエストが記録されていないとき, またはファイルの読み込みに失敗したときエラーを返す
+    pub fn load(&self, request: &Request) -> Result<String, CassetteError> {
+        let url = request.url();
+        let path = self.path(request);
+        let recorded = match fs::read_to_string(path.with_extension("url")) {
+            Ok(recorded) => recorded,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(CassetteError::NotRecorded(url))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if recorded.trim_end() != url {
+            return Err(CassetteError::NotRecorded(url));
+        }
+        Ok(fs::read_to_string(path)?)
+    }
+
+    /// レスポンスを保存する
+    ///
+    /// # Errors
+    ///
+    /// ファイルの書き込みに失敗したときエラーを返す
+    pub fn save(&self, request: &Request, body: &str) -> Result<(), CassetteError> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(request);
+        fs::write(&path, body)?;
+        fs::write(path.with_extension("url"), request.url() + "\n")?;
+        Ok(())
+    }
+}
+
+/// カセットの読み書きのエラー
+#[derive(Error, Debug)]
+pub enum CassetteError {
+    /// 再生モードで記録されていないリクエストが行われた
+    #[error("request not recorded in cassette: {0}")]
+    NotRecorded(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// URLから求めるファイル名 (64bit FNV-1aハッシュの16進表記)
+fn key(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// テスト用の一時ディレクトリ
+    pub(crate) fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("crd-api-test-{:016x}", fastrand::u64(..)))
+    }
+
+    #[test]
+    fn key_test() {
+        assert_eq!(key(""), "cbf29ce484222325");
+        assert_eq!(key("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn save_load_test() {
+        let dir = temp_dir();
+        let cassette = Cassette::record(&dir);
+        let request = Request::new("rust");
+        assert!(matches!(
+            cassette.load(&request),
+            Err(CassetteError::NotRecorded(url)) if url == request.url()
+        ));
+        cassette.save(&request, "<result_set/>").unwrap();
+        assert_eq!(cassette.load(&request).unwrap(), "<result_set/>");
+        assert_eq!(
+            fs::read_to_string(cassette.path(&request).with_extension("url")).unwrap(),
+            request.url() + "\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
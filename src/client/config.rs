@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use super::{HostHeader, InvalidRateLimitError, RateLimit, RateLimiter, RetryPolicy};
+use crate::request::DEFAULT_ENDPOINT;
+
+/// [`ClientBuilder`](super::ClientBuilder) と `blocking::ClientBuilder` で共通の設定
+#[derive(Debug, Default)]
+pub(super) struct Config {
+    pub(super) base_url: Option<String>,
+    pub(super) host_header: HostHeader,
+    pub(super) user_agent: Option<String>,
+    pub(super) timeout: Option<Duration>,
+    pub(super) connect_timeout: Option<Duration>,
+    pub(super) retry_policy: Option<RetryPolicy>,
+    pub(super) rate_limit: Option<RateLimit>,
+    pub(super) rate_limiter: Option<RateLimiter>,
+}
+
+impl Config {
+    /// エンドポイントのURL
+    pub(super) fn endpoint(&self) -> String {
+        self.base_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+    }
+
+    /// リクエスト頻度の制限
+    ///
+    /// [`RateLimit`] が指定された場合は新しく作成し, 設定が正しくなければエラーを返す
+    pub(super) fn rate_limiter(&self) -> Result<Option<RateLimiter>, InvalidRateLimitError> {
+        match &self.rate_limit {
+            Some(rate_limit) => RateLimiter::try_new(rate_limit.clone()).map(Some),
+            None => Ok(self.rate_limiter.clone()),
+        }
+    }
+}
+
+/// `config: Config` フィールドを持つビルダーに共通の設定メソッドを定義する
+macro_rules! config_setters {
+    () => {
+        /// エンドポイントのURL (デフォルト: [`DEFAULT_ENDPOINT`](crate::request::DEFAULT_ENDPOINT))
+        pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+            self.config.base_url = Some(base_url.into());
+            self
+        }
+
+        /// `Host` ヘッダーの指定方法 (デフォルト: [`HostHeader::FromUrl`](crate::client::HostHeader::FromUrl))
+        pub fn host_header(mut self, host_header: crate::client::HostHeader) -> Self {
+            self.config.host_header = host_header;
+            self
+        }
+
+        /// User-Agent (デフォルト: [`DEFAULT_USER_AGENT`](crate::client::DEFAULT_USER_AGENT))
+        pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+            self.config.user_agent = Some(user_agent.into());
+            self
+        }
+
+        /// リクエスト全体のタイムアウト
+        pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+            self.config.timeout = Some(timeout);
+            self
+        }
+
+        /// 接続のタイムアウト
+        pub fn connect_timeout(mut self, timeout: std::time::Duration) -> Self {
+            self.config.connect_timeout = Some(timeout);
+            self
+        }
+
+        /// 再試行の設定 (デフォルト: 再試行しない)
+        pub fn retry_policy(mut self, retry_policy: crate::client::RetryPolicy) -> Self {
+            self.config.retry_policy = Some(retry_policy);
+            self
+        }
+
+        /// リクエスト頻度の制限 (デフォルト: 制限しない)
+        ///
+        /// 設定が正しくない場合は `build` がエラーを返す
+        pub fn rate_limit(mut self, rate_limit: crate::client::RateLimit) -> Self {
+            self.config.rate_limit = Some(rate_limit);
+            self.config.rate_limiter = None;
+            self
+        }
+
+        /// 他のクライアントと共有するリクエスト頻度の制限
+        pub fn rate_limiter(mut self, rate_limiter: crate::client::RateLimiter) -> Self {
+            self.config.rate_limit = None;
+            self.config.rate_limiter = Some(rate_limiter);
+            self
+        }
+    };
+}
+
+/// 設定から `reqwest` のクライアントを作成する
+///
+/// `reqwest::ClientBuilder` と `reqwest::blocking::ClientBuilder` は同じ名前のメソッドを持つため,
+/// どちらのビルダーにも使用できる
+macro_rules! build_http_client {
+    ($builder:expr, $config:expr) => {{
+        let config: &crate::client::config::Config = $config;
+        let mut builder = $builder.user_agent(
+            config
+                .user_agent
+                .as_deref()
+                .unwrap_or(crate::client::DEFAULT_USER_AGENT),
+        );
+        if let crate::client::HostHeader::Fixed(host) = &config.host_header {
+            let headers =
+                reqwest::header::HeaderMap::from_iter([(reqwest::header::HOST, host.clone())]);
+            builder = builder.default_headers(headers);
+        }
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder.build()
+    }};
+}
@@ -0,0 +1,299 @@
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufRead, AsyncRead, ReadBuf},
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+/// リクエスト頻度の制限の設定
+///
+/// # Example
+///
+/// ```
+/// use crd_api::client::{Client, RateLimit};
+///
+/// fn main() -> anyhow::Result<()> {
+///     // 1秒あたり2リクエスト, 同時に1リクエストまでに制限する
+///     let client = Client::builder()
+///         .rate_limit(RateLimit {
+///             requests_per_second: 2.0,
+///             burst: 1,
+///             max_concurrency: Some(1),
+///         })
+///         .build()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    /// 1秒あたりのリクエスト数
+    ///
+    /// 正の値でなければならない. [`f64::INFINITY`] の場合は頻度を制限せず,
+    /// [`max_concurrency`](Self::max_concurrency) のみ制限する
+    pub requests_per_second: f64,
+
+    /// 連続して送信できるリクエスト数
+    pub burst: u32,
+
+    /// 同時に送信できるリクエスト数
+    ///
+    /// [`None`] の場合は制限しない
+    pub max_concurrency: Option<usize>,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 1.0,
+            burst: 1,
+            max_concurrency: Some(1),
+        }
+    }
+}
+
+/// リクエスト頻度の制限の設定が正しくない
+///
+/// 値は指定された [`requests_per_second`](RateLimit::requests_per_second)
+#[derive(Error, Debug, Clone, Copy)]
+#[error("requests_per_second must be positive, got {0}")]
+pub struct InvalidRateLimitError(pub f64);
+
+/// トークンバケット方式のリクエスト頻度の制限
+///
+/// 複製したものは状態を共有する
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: RateLimit,
+    bucket: Arc<Mutex<Bucket>>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// 制限を作成する
+    ///
+    /// # Panics
+    ///
+    /// [`try_new`](Self::try_new) がエラーを返す場合はパニックする
+    pub fn new(config: RateLimit) -> Self {
+        Self::try_new(config).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// 制限を作成する
+    ///
+    /// # Errors
+    ///
+    /// [`requests_per_second`](RateLimit::requests_per_second) が正の値でない場合 (`0`, 負の値, NaN) はエラーを返す
+    pub fn try_new(config: RateLimit) -> Result<Self, InvalidRateLimitError> {
+        let rate = config.requests_per_second;
+        if rate.is_nan() || rate <= 0.0 {
+            return Err(InvalidRateLimitError(rate));
+        }
+        let burst = config.burst.max(1) as f64;
+        Ok(Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            })),
+            semaphore: config
+                .max_concurrency
+                .map(|n| Arc::new(Semaphore::new(n.max(1)))),
+            config,
+        })
+    }
+
+    /// 設定
+    pub fn config(&self) -> &RateLimit {
+        &self.config
+    }
+
+    /// リクエストを送信できるまで待機する
+    ///
+    /// 返却された値を破棄するまで同時に送信できるリクエスト数を消費する
+    pub async fn acquire(&self) -> RateLimitPermit {
+        let permit = match &self.semaphore {
+            Some(semaphore) => Some(semaphore.clone().acquire_owned().await.unwrap()),
+            None => None,
+        };
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        RateLimitPermit { _permit: permit }
+    }
+
+    /// リクエストを送信できるまで現在のスレッドをブロックする
+    ///
+    /// 返却された値を破棄するまで同時に送信できるリクエスト数を消費する
+    #[cfg(feature = "blocking")]
+    pub fn acquire_blocking(&self) -> RateLimitPermit {
+        let permit = self.semaphore.as_ref().map(|semaphore| {
+            futures::executor::block_on(semaphore.clone().acquire_owned()).unwrap()
+        });
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        RateLimitPermit { _permit: permit }
+    }
+
+    /// トークンを1つ予約し, 使用できるまでの待機時間を返す
+    fn reserve(&self) -> Duration {
+        let rate = self.config.requests_per_second;
+        if rate.is_infinite() {
+            return Duration::ZERO;
+        }
+        let burst = self.config.burst.max(1) as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst) - 1.0;
+        bucket.updated = now;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
+
+/// [`RateLimiter::acquire`] で取得した送信の許可
+#[derive(Debug)]
+pub struct RateLimitPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// 送信の許可を保持したままレスポンスを読み込むリーダー
+///
+/// リーダーを破棄するまで同時に送信できるリクエスト数を消費する
+#[derive(Debug)]
+pub(crate) struct PermitReader<R> {
+    inner: R,
+    _permit: Option<RateLimitPermit>,
+}
+
+impl<R> PermitReader<R> {
+    pub(crate) fn new(inner: R, permit: Option<RateLimitPermit>) -> Self {
+        Self {
+            inner,
+            _permit: permit,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for PermitReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncBufRead for PermitReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        Pin::new(&mut self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        Pin::new(&mut self.get_mut().inner).consume(amt)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<R: std::io::Read> std::io::Read for PermitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl<R: std::io::BufRead> std::io::BufRead for PermitReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_test() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 10.0,
+            burst: 2,
+            max_concurrency: None,
+        });
+        let start = Instant::now();
+        for _ in 0..6 {
+            limiter.clone().acquire().await;
+        }
+        // 2件は即時, 残り4件は100msごと
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refill_test() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: 10.0,
+            burst: 1,
+            max_concurrency: None,
+        });
+        limiter.acquire().await;
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_rate_test() {
+        let limiter = RateLimiter::new(RateLimit {
+            requests_per_second: f64::INFINITY,
+            burst: 1,
+            max_concurrency: None,
+        });
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn invalid_rate_test() {
+        for rate in [0.0, -1.0, f64::NAN] {
+            let result = RateLimiter::try_new(RateLimit {
+                requests_per_second: rate,
+                ..Default::default()
+            });
+            let e = result.unwrap_err();
+            assert_eq!(e.0.to_bits(), rate.to_bits());
+            assert_eq!(
+                e.to_string(),
+                format!("requests_per_second must be positive, got {rate}")
+            );
+        }
+    }
+}
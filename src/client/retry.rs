@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::error::Error;
+
+/// 再試行の設定
+///
+/// 待機時間は `base_delay * 2^(n - 1)` (`n` は失敗した回数) を `max_delay` で制限し,
+/// `jitter` の割合だけランダムに短縮したものとなる
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use crd_api::client::{Client, RetryPolicy};
+///
+/// fn main() -> anyhow::Result<()> {
+///     let client = Client::builder()
+///         .retry_policy(RetryPolicy {
+///             max_attempts: 5,
+///             base_delay: Duration::from_secs(1),
+///             ..Default::default()
+///         })
+///         .build()?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// 最大試行回数 (初回を含む)
+    pub max_attempts: u32,
+
+    /// 初回の再試行までの待機時間
+    pub base_delay: Duration,
+
+    /// 待機時間の上限
+    pub max_delay: Duration,
+
+    /// 待機時間をランダムに短縮する割合 (`0.0` から `1.0`)
+    pub jitter: f64,
+
+    /// 接続の失敗やタイムアウトなどの通信エラーを再試行する
+    pub retry_request_errors: bool,
+
+    /// 再試行で解消する可能性のあるAPIのエラー ([`ApiErrorCode::is_retryable`](crate::error::ApiErrorCode::is_retryable)) を再試行する
+    pub retry_api_errors: bool,
+
+    /// 返却されたXMLの解析エラーを再試行する
+    pub retry_parse_errors: bool,
+
+    /// 再試行するHTTPステータス
+    pub retry_statuses: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retry_request_errors: true,
+            retry_api_errors: true,
+            retry_parse_errors: false,
+            retry_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// エラーが再試行の対象なら [`true`] を返す
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        match error {
+            Error::Request(e) => match e.status() {
+                Some(status) => self.retry_statuses.contains(&status),
+                None => self.retry_request_errors && !e.is_builder() && !e.is_redirect(),
+            },
+            Error::Status(status) => self.retry_statuses.contains(status),
+            Error::Api(e) => self.retry_api_errors && e.is_retryable(),
+            Error::De(_) => self.retry_parse_errors,
+            _ => false,
+        }
+    }
+
+    /// `attempt` 回目の試行が失敗した後の待機時間
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let delay = self.base_delay.saturating_mul(1 << exp).min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0) * fastrand::f64();
+        delay.mul_f64(1.0 - jitter)
+    }
+}
+
+/// `attempt` 回目の試行が `error` で失敗した後, 再試行するまでの待機時間を返す
+///
+/// 再試行しない場合は呼び出し元に返すエラーを返す.
+/// 2回以上試行していた場合は [`Error::Retry`] とする
+pub(crate) fn retry_delay(
+    policy: Option<&RetryPolicy>,
+    attempt: u32,
+    error: Error,
+) -> Result<Duration, Error> {
+    match policy {
+        Some(policy) if attempt < policy.max_attempts && policy.is_retryable(&error) => {
+            Ok(policy.delay(attempt))
+        }
+        _ if attempt > 1 => Err(Error::Retry {
+            attempts: attempt,
+            source: error.into(),
+        }),
+        _ => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ApiErrors;
+
+    use super::*;
+
+    #[test]
+    fn delay_test() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(100), Duration::from_millis(500));
+
+        let policy = RetryPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay > Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn is_retryable_test() {
+        let policy = RetryPolicy::default();
+        let api_error = |code: &str| {
+            Error::Api(ApiErrors::from_xml(&format!(
+                "<result_set>
+                <results_cd>1</results_cd>
+                <err_list><err_item><err_code>{code}</err_code><err_fld/><err_msg/></err_item></err_list>
+                </result_set>"
+            )).unwrap())
+        };
+        assert!(policy.is_retryable(&api_error("0901")));
+        assert!(!policy.is_retryable(&api_error("0101")));
+        assert!(policy.is_retryable(&Error::Status(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(!policy.is_retryable(&Error::Status(StatusCode::NOT_FOUND)));
+        let policy = RetryPolicy {
+            retry_api_errors: false,
+            ..policy
+        };
+        assert!(!policy.is_retryable(&api_error("0901")));
+    }
+
+    #[test]
+    fn retry_delay_test() {
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            base_delay: Duration::from_millis(100),
+            jitter: 0.0,
+            ..Default::default()
+        };
+        let unavailable = || Error::Status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            retry_delay(Some(&policy), 1, unavailable()).unwrap(),
+            Duration::from_millis(100)
+        );
+        let err = retry_delay(Some(&policy), 2, unavailable()).unwrap_err();
+        assert_eq!(err.attempts(), 2);
+        let err = retry_delay(Some(&policy), 1, Error::Status(StatusCode::NOT_FOUND)).unwrap_err();
+        assert!(matches!(err, Error::Status(StatusCode::NOT_FOUND)));
+        let err = retry_delay(None, 1, unavailable()).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Status(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+    }
+}
@@ -1,6 +1,23 @@
-use std::fmt::Display;
+use std::{
+    fmt::{Display, Write},
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::request::SearchType;
+
+mod parse;
+mod similar;
+mod visit;
+
+pub use parse::{parse, ParseError, ParseErrorKind};
+pub use similar::{SimilarOptions, SimilarSource};
+pub use visit::{
+    fold_query, fold_scoped_clause, walk_query, walk_scoped_clause, Fold, MapIndex, MapTerms,
+    Prune, Visitor,
+};
 
 /// CQLフォーマットの検索クエリー
 ///
@@ -13,8 +30,12 @@ use serde::{Deserialize, Serialize};
 /// // 任意の項目に "rust" と "language" の両方を含む要素を指定するクエリー
 /// let query = Query::all("anywhere", &["rust", "language"]);
 /// let cql = query.to_string();
-/// println!("{cql}")
+/// println!("{cql}");
 /// // anywhere all rust language
+///
+/// // 文字列から変換
+/// let parsed: Query = cql.parse().unwrap();
+/// assert_eq!(parsed, query);
 /// ```
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Query {
@@ -22,7 +43,7 @@ pub enum Query {
     SearchClause {
         index: Index,
         relation: Relation,
-        search_term: Vec<String>,
+        search_term: Vec<Term>,
     },
 
     /// 複数の検索句の結合
@@ -44,7 +65,7 @@ impl Query {
         Self::SearchClause {
             index: index.into(),
             relation: Relation::All,
-            search_term: search_term.iter().map(|s| s.to_string()).collect(),
+            search_term: search_term.iter().map(|&s| s.into()).collect(),
         }
     }
 
@@ -53,7 +74,7 @@ impl Query {
         Self::SearchClause {
             index: index.into(),
             relation: Relation::Any,
-            search_term: search_term.iter().map(|s| s.to_string()).collect(),
+            search_term: search_term.iter().map(|&s| s.into()).collect(),
         }
     }
 
@@ -62,7 +83,7 @@ impl Query {
         Self::SearchClause {
             index: index.into(),
             relation: Relation::Equal,
-            search_term: search_term.iter().map(|s| s.to_string()).collect(),
+            search_term: search_term.iter().map(|&s| s.into()).collect(),
         }
     }
 
@@ -92,6 +113,117 @@ impl Query {
             right: right.into(),
         }
     }
+
+    /// 同じ真偽演算子 (`and`, `or`) の入れ子を平坦化し, n項の正規形に変換する
+    ///
+    /// 結合の順序が意味を持つ `not` は二項のまま残す.
+    /// [`Query`] に戻す場合は, 解析結果と同じく左から順に結合した木になる
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crd_api::cql::{Boolean, Normalized, Query};
+    ///
+    /// let (a, b, c) = (
+    ///     Query::any("question", &["本"]),
+    ///     Query::any("answer", &["音楽"]),
+    ///     Query::equal("solution", &["0"]),
+    /// );
+    /// let query = a.clone().and(b.clone().and(c.clone()));
+    /// let normalized = query.normalize();
+    /// assert_eq!(normalized.boolean(), Some(Boolean::And));
+    /// assert_eq!(
+    ///     normalized.operands(),
+    ///     [a.clone().normalize(), b.clone().normalize(), c.clone().normalize()]
+    /// );
+    /// assert_eq!(normalized.to_string(), "question any 本 and answer any 音楽 and solution = 0");
+    /// assert_eq!(Query::from(normalized), a.and(b).and(c));
+    /// ```
+    pub fn normalize(self) -> Normalized {
+        match self {
+            Self::SearchClause {
+                index,
+                relation,
+                search_term,
+            } => Normalized::SearchClause {
+                index,
+                relation,
+                search_term,
+            },
+            Self::ScopedClause {
+                left,
+                boolean: Boolean::Not,
+                right,
+            } => Normalized::ScopedClause {
+                boolean: Boolean::Not,
+                operands: vec![left.normalize(), right.normalize()],
+            },
+            Self::ScopedClause { boolean, .. } => {
+                let mut operands = Vec::new();
+                self.into_operands(boolean, &mut operands);
+                Normalized::ScopedClause {
+                    boolean,
+                    operands: operands.into_iter().map(Self::normalize).collect(),
+                }
+            }
+        }
+    }
+
+    fn into_operands(self, boolean: Boolean, operands: &mut Vec<Self>) {
+        match self {
+            Self::ScopedClause {
+                left,
+                boolean: b,
+                right,
+            } if b == boolean => {
+                left.into_operands(boolean, operands);
+                right.into_operands(boolean, operands);
+            }
+            query => operands.push(query),
+        }
+    }
+
+    /// クエリーの全ての項目が指定した検索区分で使用できるか検証する
+    ///
+    /// # Errors
+    ///
+    /// 使用できない項目が含まれているとき, 最初に現れた項目のエラーを返す
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crd_api::{cql::{Query, ReferenceIndex}, request::SearchType};
+    ///
+    /// let query = Query::any(ReferenceIndex::Question, &["本"]);
+    /// assert!(query.validate_for(SearchType::Reference).is_ok());
+    /// assert!(query.validate_for(SearchType::Manual).is_err());
+    /// ```
+    pub fn validate_for(&self, search_type: SearchType) -> Result<(), ValidationError> {
+        match self {
+            Self::SearchClause { index, .. } => {
+                if index.is_valid_for(search_type) {
+                    Ok(())
+                } else {
+                    Err(ValidationError {
+                        index: index.clone(),
+                        search_type,
+                    })
+                }
+            }
+            Self::ScopedClause { left, right, .. } => {
+                left.validate_for(search_type)?;
+                right.validate_for(search_type)
+            }
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
 }
 
 impl Display for Query {
@@ -102,156 +234,159 @@ impl Display for Query {
                 boolean,
                 right,
             } => {
-                write!(f, "{left} {boolean} ")?;
-                match **right {
-                    Self::ScopedClause { .. } => write!(f, "( {right} )")?,
-                    Self::SearchClause { .. } => write!(f, "{right}")?,
+                // 真偽演算子は優先順位を持たず左から順に結合するが, サーバーの解釈に依存しないよう,
+                // 結合の順序によらない同じ演算子 (`and`, `or`) の連続以外は括弧で囲む
+                let operand = |f: &mut std::fmt::Formatter<'_>, query: &Self| match query {
+                    Self::ScopedClause { boolean: b, .. }
+                        if b != boolean || *boolean == Boolean::Not =>
+                    {
+                        write!(f, "( {query} )")
+                    }
+                    _ => write!(f, "{query}"),
                 };
+                operand(f, left)?;
+                write!(f, " {boolean} ")?;
+                operand(f, right)?;
             }
             Self::SearchClause {
                 index,
                 relation,
                 search_term,
             } => {
-                let search_term = search_term.join(" ");
-                write!(f, "{index} {relation} {search_term}")?;
+                write!(f, "{index} {relation}")?;
+                for term in search_term {
+                    write!(f, " {term}")?;
+                }
             }
         }
         Ok(())
     }
 }
 
+/// [`Query::normalize`] で変換したn項の正規形
+///
+/// 同じ真偽演算子 (`and`, `or`) で結合された検索句は1つの [`ScopedClause`](Self::ScopedClause) にまとめる
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Normalized {
+    /// 単一の検索句
+    SearchClause {
+        index: Index,
+        relation: Relation,
+        search_term: Vec<Term>,
+    },
+
+    /// 複数の検索句の結合
+    ///
+    /// `and`, `or` の場合は同じ演算子の [`ScopedClause`](Self::ScopedClause) を含まない2つ以上の検索句,
+    /// `not` の場合は常に2つの検索句を持つ
+    ScopedClause {
+        boolean: Boolean,
+        operands: Vec<Normalized>,
+    },
+}
+
+impl Normalized {
+    /// 最上位の真偽演算子. 単一の検索句の場合は [`None`]
+    pub fn boolean(&self) -> Option<Boolean> {
+        match self {
+            Self::SearchClause { .. } => None,
+            Self::ScopedClause { boolean, .. } => Some(*boolean),
+        }
+    }
+
+    /// 最上位の真偽演算子で結合された検索句. 単一の検索句の場合は自身のみを返す
+    pub fn operands(&self) -> &[Self] {
+        match self {
+            Self::SearchClause { .. } => std::slice::from_ref(self),
+            Self::ScopedClause { operands, .. } => operands,
+        }
+    }
+}
+
+impl From<Normalized> for Query {
+    fn from(normalized: Normalized) -> Self {
+        match normalized {
+            Normalized::SearchClause {
+                index,
+                relation,
+                search_term,
+            } => Self::SearchClause {
+                index,
+                relation,
+                search_term,
+            },
+            Normalized::ScopedClause { boolean, operands } => operands
+                .into_iter()
+                .map(Self::from)
+                .reduce(|left, right| Self::ScopedClause {
+                    left: left.into(),
+                    boolean,
+                    right: right.into(),
+                })
+                .expect("scoped clause must have operands"),
+        }
+    }
+}
+
+impl Display for Normalized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ScopedClause { boolean, operands } => {
+                // 同じ演算子の入れ子は平坦化されているため, 結合された検索句は全て括弧で囲む
+                for (i, operand) in operands.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {boolean} ")?;
+                    }
+                    match operand {
+                        Self::ScopedClause { .. } => write!(f, "( {operand} )")?,
+                        Self::SearchClause { .. } => write!(f, "{operand}")?,
+                    }
+                }
+                Ok(())
+            }
+            Self::SearchClause {
+                index,
+                relation,
+                search_term,
+            } => {
+                write!(f, "{index} {relation}")?;
+                for term in search_term {
+                    write!(f, " {term}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 /// クエリー対象項目
 ///
 /// 参照: <https://crd.ndl.go.jp/jp/help/general/api_spec_2.html#cql>
 ///
-/// - レファレンス事例
-///     - `anywhere`: 全項目 (簡易検索)
-///         - 簡易検索と同範囲での検索となる
-///     - `question`: 質問
-///     - `reg-id`: 管理番号
-///         - 前方一致
-///     - `answer`: 回答
-///     - `solution`: 解決／未解決
-///         - 完全一致
-///             - `0`: 解決
-///             - `1`: 未解決
-///             - `resolved`: 解決
-///             - `unresolved`: 未解決
-///     - `keyword`: キーワード
-///     - `ndc`: NDC
-///         - 前方一致
-///     - `res-type`: 調査種別
-///     - `con-type`: 内容種別
-///     - `bibl-desc`: 参考資料 (書誌的事項等)
-///         - 参考資料の書誌的事項と備考を検索する
-///     - `bibl-isbn`: 参考資料 (ISBN)
-///     - `ans-proc`: 回答プロセス
-///     - `referral`: 照会先
-///     - `pre-res`: 事前調査事項
-///     - `note`: 備考
-///     - `ptn-type`: 質問者区分
-///     - `contri`: 寄与者
-///     - `sys-id`: 登録番号
-///         - 完全一致
-///     - `lib-name`: 提供館名
-/// - 調べ方マニュアル
-///     - `anywhere`: 全項目 (簡易検索)
-///         - 簡易検索と同範囲での検索となる
-///     - `theme`: 調査テーマ
-///     - `reg-id`: 管理番号
-///         - 前方一致
-///     - `guide`: 調べ方
-///     - `completion`: 完成／未完成
-///         - 完全一致
-///             - `0`: 完成
-///             - `1`: 未完成
-///             - `complete`: 完成
-///             - `incomplete`: 未完成
-///     - `keyword`: キーワード
-///     - `ndc`: NDC
-///         - 前方一致
-///     - `bibl-desc`: 参考資料 (書誌的事項等)
-///         - 参考資料の書誌的事項と備考を検索する
-///     - `bibl-isbn`: 参考資料 (ISBN)
-///     - `note`: 備考
-///     - `sys-id`: 登録番号
-///         - 完全一致
-///     - `lib-name`: 提供館名
-/// - 特別コレクション
-///     - `anywhere`: 全項目 (簡易検索)
-///         - 簡易検索と同範囲での検索となる。
-///     - `col-name`: コレクション名
-///         - コレクション名、コレクション名ヨミを検索する
-///     - `reg-id`: 管理番号
-///         - 前方一致
-///     - `outline`: 内容
-///     - `origin`: 来歴
-///     - `restriction`: 利用条件
-///     - `catalog`: 目録等
-///     - `literature`: 紹介文献
-///     - `number`: 所蔵点数
-///     - `continue`: 継続
-///         - 完全一致
-///             - `0`: 継続有
-///             - `1`: 継続無
-///             - `continue`: 継続有
-///             - `discontinued`: 継続無
-///     - `keyword`: キーワード
-///     - `ndc`: NDC
-///         - 前方一致
-///     - `note`: 備考
-///     - `sys-id`: 登録番号
-///         - 完全一致
-///     - `lib-name`: 提供館名
-/// - 参加館プロファイル
-///     - `anywhere`: 全項目 (簡易検索)
-///         - 簡易検索と同範囲での検索となる
-///     - `lib-type`: 館種
-///         - 完全一致
-///         - 下記コード値、デコード値ともに許可する
-///             - `11`: 国立国会図書館(東京本館)
-///             - `12`: 国立国会図書館(関西館)
-///             - `13`: 国立国会図書館(国際子ども図書館)
-///             - `14`: 国立国会図書館(支部図書館)
-///             - `21`: 公共図書館(都道府県立)
-///             - `22`: 公共図書館(政令都市立)
-///             - `23`: 公共図書館(市立・特別区立)
-///             - `24`: 公共図書館(町村立)
-///             - `31`: 大学図書館(国立大学)
-///             - `32`: 大学図書館(公立大学)
-///             - `33`: 大学図書館(私立大学)
-///             - `35`: 大学図書館(高等専門)
-///             - `41`: 専門図書館(国公立)
-///             - `42`: 専門図書館(公益法人)
-///             - `43`: 専門図書館(企業)
-///             - `44`: 専門図書館(その他)
-///             - `51`: 学校図書館(高等学校)
-///             - `52`: 学校図書館(中学校)
-///             - `53`: 学校図書館(小学校)
-///             - `54`: 学校図書館(その他)
-///             - `90`: アーカイブズ
-///     - `lib-name`: 図書館名
-///         - 以下を検索する
-///             - 図書館名 (正式)
-///             - 図書館名 (略式)
-///             - 図書館名ヨミ
-///     - `address`: 住所
-///         - 以下を検索する
-///             - 住所 (都道府県)
-///             - 住所 (市区町村)
-///             - 住所 (丁目・番地)
-///             - (= 住所 (検索用))
-///     - `open-info`: 開館情報
-///     - `restriction`: 利用条件
-///     - `outline`: 沿革
-///     - `feature`: 特色
-///     - `notes`: 注意事項
-///     - `access`: 交通アクセス
-///     - `isil`: ISIL
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+/// 検索区分ごとに使用できる項目は [`ReferenceIndex`], [`ManualIndex`], [`CollectionIndex`],
+/// [`ProfileIndex`] を参照. 検索区分が [`SearchType::All`] の場合は `anywhere` のみ使用可能
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Index(String);
 
+impl Index {
+    /// 項目名
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 指定した検索区分で使用できるなら [`true`] を返す
+    pub fn is_valid_for(&self, search_type: SearchType) -> bool {
+        let s = self.as_str();
+        match search_type {
+            SearchType::Reference => s.parse::<ReferenceIndex>().is_ok(),
+            SearchType::Manual => s.parse::<ManualIndex>().is_ok(),
+            SearchType::Collection => s.parse::<CollectionIndex>().is_ok(),
+            SearchType::Profile => s.parse::<ProfileIndex>().is_ok(),
+            SearchType::All => s == "anywhere",
+        }
+    }
+}
+
 impl Display for Index {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -264,6 +399,456 @@ impl From<&str> for Index {
     }
 }
 
+impl From<String> for Index {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+param_enum! {
+    /// レファレンス事例のクエリー対象項目
+    pub enum ReferenceIndex {
+        /// 全項目 (簡易検索)
+        ///
+        /// 簡易検索と同範囲での検索となる
+        Anywhere => "anywhere",
+
+        /// 質問
+        Question => "question",
+
+        /// 管理番号
+        ///
+        /// 前方一致
+        RegId => "reg-id",
+
+        /// 回答
+        Answer => "answer",
+
+        /// 解決／未解決
+        ///
+        /// 完全一致
+        /// - `0`: 解決
+        /// - `1`: 未解決
+        /// - `resolved`: 解決
+        /// - `unresolved`: 未解決
+        Solution => "solution",
+
+        /// キーワード
+        Keyword => "keyword",
+
+        /// NDC
+        ///
+        /// 前方一致
+        Ndc => "ndc",
+
+        /// 調査種別
+        ResType => "res-type",
+
+        /// 内容種別
+        ConType => "con-type",
+
+        /// 参考資料 (書誌的事項等)
+        ///
+        /// 参考資料の書誌的事項と備考を検索する
+        BiblDesc => "bibl-desc",
+
+        /// 参考資料 (ISBN)
+        BiblIsbn => "bibl-isbn",
+
+        /// 回答プロセス
+        AnsProc => "ans-proc",
+
+        /// 照会先
+        Referral => "referral",
+
+        /// 事前調査事項
+        PreRes => "pre-res",
+
+        /// 備考
+        Note => "note",
+
+        /// 質問者区分
+        PtnType => "ptn-type",
+
+        /// 寄与者
+        Contri => "contri",
+
+        /// 登録番号
+        ///
+        /// 完全一致
+        SysId => "sys-id",
+
+        /// 提供館名
+        LibName => "lib-name",
+    }
+}
+
+param_enum! {
+    /// 調べ方マニュアルのクエリー対象項目
+    pub enum ManualIndex {
+        /// 全項目 (簡易検索)
+        ///
+        /// 簡易検索と同範囲での検索となる
+        Anywhere => "anywhere",
+
+        /// 調査テーマ
+        Theme => "theme",
+
+        /// 管理番号
+        ///
+        /// 前方一致
+        RegId => "reg-id",
+
+        /// 調べ方
+        Guide => "guide",
+
+        /// 完成／未完成
+        ///
+        /// 完全一致
+        /// - `0`: 完成
+        /// - `1`: 未完成
+        /// - `complete`: 完成
+        /// - `incomplete`: 未完成
+        Completion => "completion",
+
+        /// キーワード
+        Keyword => "keyword",
+
+        /// NDC
+        ///
+        /// 前方一致
+        Ndc => "ndc",
+
+        /// 参考資料 (書誌的事項等)
+        ///
+        /// 参考資料の書誌的事項と備考を検索する
+        BiblDesc => "bibl-desc",
+
+        /// 参考資料 (ISBN)
+        BiblIsbn => "bibl-isbn",
+
+        /// 備考
+        Note => "note",
+
+        /// 登録番号
+        ///
+        /// 完全一致
+        SysId => "sys-id",
+
+        /// 提供館名
+        LibName => "lib-name",
+    }
+}
+
+param_enum! {
+    /// 特別コレクションのクエリー対象項目
+    pub enum CollectionIndex {
+        /// 全項目 (簡易検索)
+        ///
+        /// 簡易検索と同範囲での検索となる
+        Anywhere => "anywhere",
+
+        /// コレクション名
+        ///
+        /// コレクション名、コレクション名ヨミを検索する
+        ColName => "col-name",
+
+        /// 管理番号
+        ///
+        /// 前方一致
+        RegId => "reg-id",
+
+        /// 内容
+        Outline => "outline",
+
+        /// 来歴
+        Origin => "origin",
+
+        /// 利用条件
+        Restriction => "restriction",
+
+        /// 目録等
+        Catalog => "catalog",
+
+        /// 紹介文献
+        Literature => "literature",
+
+        /// 所蔵点数
+        Number => "number",
+
+        /// 継続
+        ///
+        /// 完全一致
+        /// - `0`: 継続有
+        /// - `1`: 継続無
+        /// - `continue`: 継続有
+        /// - `discontinued`: 継続無
+        Continue => "continue",
+
+        /// キーワード
+        Keyword => "keyword",
+
+        /// NDC
+        ///
+        /// 前方一致
+        Ndc => "ndc",
+
+        /// 備考
+        Note => "note",
+
+        /// 登録番号
+        ///
+        /// 完全一致
+        SysId => "sys-id",
+
+        /// 提供館名
+        LibName => "lib-name",
+    }
+}
+
+param_enum! {
+    /// 参加館プロファイルのクエリー対象項目
+    pub enum ProfileIndex {
+        /// 全項目 (簡易検索)
+        ///
+        /// 簡易検索と同範囲での検索となる
+        Anywhere => "anywhere",
+
+        /// 館種
+        ///
+        /// 完全一致. 下記コード値、デコード値ともに許可する
+        /// - `11`: 国立国会図書館(東京本館)
+        /// - `12`: 国立国会図書館(関西館)
+        /// - `13`: 国立国会図書館(国際子ども図書館)
+        /// - `14`: 国立国会図書館(支部図書館)
+        /// - `21`: 公共図書館(都道府県立)
+        /// - `22`: 公共図書館(政令都市立)
+        /// - `23`: 公共図書館(市立・特別区立)
+        /// - `24`: 公共図書館(町村立)
+        /// - `31`: 大学図書館(国立大学)
+        /// - `32`: 大学図書館(公立大学)
+        /// - `33`: 大学図書館(私立大学)
+        /// - `35`: 大学図書館(高等専門)
+        /// - `41`: 専門図書館(国公立)
+        /// - `42`: 専門図書館(公益法人)
+        /// - `43`: 専門図書館(企業)
+        /// - `44`: 専門図書館(その他)
+        /// - `51`: 学校図書館(高等学校)
+        /// - `52`: 学校図書館(中学校)
+        /// - `53`: 学校図書館(小学校)
+        /// - `54`: 学校図書館(その他)
+        /// - `90`: アーカイブズ
+        ///
+        /// [`LibType`](crate::response::LibType) で型付きのコードを扱える
+        LibType => "lib-type",
+
+        /// 図書館名
+        ///
+        /// 図書館名 (正式), 図書館名 (略式), 図書館名ヨミを検索する
+        LibName => "lib-name",
+
+        /// 住所
+        ///
+        /// 住所 (都道府県), 住所 (市区町村), 住所 (丁目・番地), 住所 (検索用) を検索する
+        Address => "address",
+
+        /// 開館情報
+        OpenInfo => "open-info",
+
+        /// 利用条件
+        Restriction => "restriction",
+
+        /// 沿革
+        Outline => "outline",
+
+        /// 特色
+        Feature => "feature",
+
+        /// 注意事項
+        Notes => "notes",
+
+        /// 交通アクセス
+        Access => "access",
+
+        /// ISIL
+        Isil => "isil",
+    }
+}
+
+macro_rules! impl_from_index {
+    ($($name:ident),*) => {
+        $(
+            impl From<$name> for Index {
+                fn from(value: $name) -> Self {
+                    Self(value.as_str().to_string())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_index!(ReferenceIndex, ManualIndex, CollectionIndex, ProfileIndex);
+
+/// 検索区分で使用できない項目がクエリーに含まれているときのエラー
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("index `{index}` is not available for search type `{search_type}`")]
+pub struct ValidationError {
+    /// 使用できない項目
+    pub index: Index,
+
+    /// 検索区分
+    pub search_type: SearchType,
+}
+
+/// 検索語
+///
+/// 引用符なしの単語と `"` で囲んだフレーズがある.
+/// 単語は空白や括弧, `"` などを含まず, 真偽演算子と同じ綴りでないことが保証される.
+/// フレーズの中の `"` と `\` は `\` でエスケープして出力する
+///
+/// # Example
+///
+/// ```
+/// use crd_api::cql::{Query, Term};
+///
+/// assert_eq!(Term::new("rust"), Term::word("rust").unwrap());
+/// assert_eq!(Term::new("and"), Term::phrase("and"));
+/// assert!(Term::word("and").is_err());
+/// assert_eq!(Term::new(r#"say "hi""#).to_string(), r#""say \"hi\"""#);
+///
+/// let query = Query::all("question", &["村上 春樹", "(小説)"]);
+/// assert_eq!(query.to_string(), r#"question all "村上 春樹" "(小説)""#);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(try_from = "TermRepr", into = "TermRepr")]
+pub struct Term {
+    text: String,
+    phrase: bool,
+}
+
+/// [`Term`] のシリアライズ形式
+#[derive(Serialize, Deserialize)]
+enum TermRepr {
+    Word(String),
+    Phrase(String),
+}
+
+impl Term {
+    /// 検索語を作成する
+    ///
+    /// 引用符なしで出力できる場合は単語, それ以外はフレーズとなる
+    pub fn new(term: impl Into<String>) -> Self {
+        let term = term.into();
+        if needs_quote(&term) {
+            Self::phrase(term)
+        } else {
+            Self {
+                text: term,
+                phrase: false,
+            }
+        }
+    }
+
+    /// 引用符なしの単語を作成する
+    ///
+    /// # Errors
+    ///
+    /// 引用符なしでは別の意味に解析される場合はエラーを返す
+    pub fn word(term: impl Into<String>) -> Result<Self, InvalidWordError> {
+        let term = term.into();
+        if needs_quote(&term) {
+            return Err(InvalidWordError(term));
+        }
+        Ok(Self {
+            text: term,
+            phrase: false,
+        })
+    }
+
+    /// `"` で囲んだフレーズを作成する
+    pub fn phrase(term: impl Into<String>) -> Self {
+        Self {
+            text: term.into(),
+            phrase: true,
+        }
+    }
+
+    /// 検索語の文字列
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// フレーズなら [`true`] を返す
+    pub fn is_phrase(&self) -> bool {
+        self.phrase
+    }
+}
+
+/// 引用符なしでは別の意味に解析される語を単語として作成しようとしたときのエラー
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("`{0}` cannot be used as an unquoted word")]
+pub struct InvalidWordError(pub String);
+
+/// 引用符なしでは別の意味に解析される語なら [`true`] を返す
+fn needs_quote(term: &str) -> bool {
+    term.is_empty()
+        || ["and", "or", "not"]
+            .iter()
+            .any(|b| term.eq_ignore_ascii_case(b))
+        || term
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '=' | '"' | '\\'))
+}
+
+impl TryFrom<TermRepr> for Term {
+    type Error = InvalidWordError;
+
+    fn try_from(value: TermRepr) -> Result<Self, Self::Error> {
+        match value {
+            TermRepr::Word(s) => Self::word(s),
+            TermRepr::Phrase(s) => Ok(Self::phrase(s)),
+        }
+    }
+}
+
+impl From<Term> for TermRepr {
+    fn from(value: Term) -> Self {
+        if value.phrase {
+            Self::Phrase(value.text)
+        } else {
+            Self::Word(value.text)
+        }
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.phrase {
+            return write!(f, "{}", self.text);
+        }
+        f.write_char('"')?;
+        for c in self.text.chars() {
+            if matches!(c, '"' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+}
+
+impl From<&str> for Term {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for Term {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Relation {
     /// 複数のキーワードをAND演算で検索する
@@ -286,7 +871,7 @@ impl Display for Relation {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Boolean {
     /// 2つの検索句をAND条件で結合する
     And,
@@ -317,12 +902,12 @@ mod tests {
         let q1 = Query::SearchClause {
             index: "question".into(),
             relation: Relation::Equal,
-            search_term: vec!["rust".to_string()],
+            search_term: vec!["rust".into()],
         };
         let q2 = Query::SearchClause {
             index: "answer".into(),
             relation: Relation::Any,
-            search_term: vec!["programming".to_string(), "language".to_string()],
+            search_term: vec!["programming".into(), "language".into()],
         };
         let q = Query::ScopedClause {
             left: q1.into(),
@@ -339,6 +924,45 @@ mod tests {
         assert_eq!(q.to_string(), "question any 本 and answer any 村上春樹")
     }
 
+    #[test]
+    fn typed_index_test() {
+        let q = Query::any(ReferenceIndex::Question, &["本"])
+            .and(Query::equal(ReferenceIndex::Solution, &["0"]));
+        assert_eq!(q.to_string(), "question any 本 and solution = 0");
+        assert_eq!(Index::from(ProfileIndex::LibType).as_str(), "lib-type");
+        assert_eq!(
+            "col-name".parse::<CollectionIndex>().unwrap(),
+            CollectionIndex::ColName
+        );
+        assert!("theme".parse::<ReferenceIndex>().is_err());
+    }
+
+    #[test]
+    fn validate_for_test() {
+        let q = Query::any("question", &["本"]).and(Query::any("theme", &["音楽"]));
+        assert_eq!(
+            q.validate_for(SearchType::Reference),
+            Err(ValidationError {
+                index: "theme".into(),
+                search_type: SearchType::Reference,
+            })
+        );
+        assert_eq!(
+            q.validate_for(SearchType::Manual).unwrap_err().to_string(),
+            "index `question` is not available for search type `manual`"
+        );
+        let q = Query::new(&["rust"]).not(Query::any("answer", &["go"]));
+        assert!(q.validate_for(SearchType::Reference).is_ok());
+        assert!(q.validate_for(SearchType::All).is_err());
+        assert!(Query::new(&["rust"]).validate_for(SearchType::All).is_ok());
+        assert!(Query::equal("lib-type", &["11"])
+            .validate_for(SearchType::Profile)
+            .is_ok());
+        assert!(Query::equal("lib-type", &["11"])
+            .validate_for(SearchType::Collection)
+            .is_err());
+    }
+
     #[test]
     fn cql_test3() {
         let q1 = Query::any("question", &["本", "音楽"])
@@ -349,14 +973,14 @@ mod tests {
                 left: Query::SearchClause {
                     index: "question".into(),
                     relation: Relation::Any,
-                    search_term: vec!["本".to_string(), "音楽".to_string()],
+                    search_term: vec!["本".into(), "音楽".into()],
                 }
                 .into(),
                 boolean: Boolean::And,
                 right: Query::SearchClause {
                     index: "solution".into(),
                     relation: Relation::Equal,
-                    search_term: vec!["resolved".to_string()],
+                    search_term: vec!["resolved".into()],
                 }
                 .into(),
             }
@@ -365,10 +989,118 @@ mod tests {
             right: Query::SearchClause {
                 index: "ptn-type".into(),
                 relation: Relation::Equal,
-                search_term: vec!["学生".to_string()],
+                search_term: vec!["学生".into()],
             }
             .into(),
         };
         assert_eq!(q1, q2);
     }
+
+    #[test]
+    fn term_test() {
+        assert!(!Term::word("rust").unwrap().is_phrase());
+        for s in ["", "and", "OR", "a b", "(a)", "a=b", r#"a"b"#, r"a\b"] {
+            assert_eq!(Term::word(s), Err(InvalidWordError(s.to_string())));
+            assert!(Term::new(s).is_phrase());
+        }
+        assert_eq!(Term::phrase("and").to_string(), r#""and""#);
+
+        let json = serde_json::to_string(&Term::new("rust")).unwrap();
+        assert_eq!(json, r#"{"Word":"rust"}"#);
+        assert!(serde_json::from_str::<Term>(r#"{"Word":"and"}"#).is_err());
+        assert_eq!(
+            serde_json::from_str::<Term>(r#"{"Phrase":"and"}"#).unwrap(),
+            Term::phrase("and")
+        );
+    }
+
+    #[test]
+    fn parenthesize_test() {
+        let a = || Query::any("question", &["a"]);
+        let b = || Query::any("question", &["b"]);
+        let c = || Query::any("question", &["c"]);
+        let cases = [
+            (
+                a().or(b()).and(c()),
+                "( question any a or question any b ) and question any c",
+            ),
+            (
+                a().and(b().or(c())),
+                "question any a and ( question any b or question any c )",
+            ),
+            (
+                a().and(b()).and(c()),
+                "question any a and question any b and question any c",
+            ),
+            (
+                a().or(b().or(c())),
+                "question any a or question any b or question any c",
+            ),
+            (
+                a().not(b()).not(c()),
+                "( question any a not question any b ) not question any c",
+            ),
+            (
+                a().not(b().not(c())),
+                "question any a not ( question any b not question any c )",
+            ),
+            (
+                a().and(b()).not(c()),
+                "( question any a and question any b ) not question any c",
+            ),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(query.to_string(), expected);
+            assert_eq!(parse(expected).unwrap(), query.clone().normalize().into());
+            assert_eq!(query.normalize().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn normalize_test() {
+        let q = |s: &str| Query::equal("keyword", &[s]);
+        let n = |s: &str| q(s).normalize();
+        let query = q("a")
+            .and(q("b").and(q("c")))
+            .or(q("d").or(q("e").or(q("f")).not(q("g"))));
+        let normalized = query.clone().normalize();
+        assert_eq!(
+            normalized,
+            Normalized::ScopedClause {
+                boolean: Boolean::Or,
+                operands: vec![
+                    Normalized::ScopedClause {
+                        boolean: Boolean::And,
+                        operands: vec![n("a"), n("b"), n("c")],
+                    },
+                    n("d"),
+                    Normalized::ScopedClause {
+                        boolean: Boolean::Not,
+                        operands: vec![
+                            Normalized::ScopedClause {
+                                boolean: Boolean::Or,
+                                operands: vec![n("e"), n("f")],
+                            },
+                            n("g"),
+                        ],
+                    },
+                ],
+            }
+        );
+        assert_eq!(normalized.boolean(), Some(Boolean::Or));
+        assert_eq!(normalized.operands().len(), 3);
+        assert_eq!(normalized.to_string(), query.to_string());
+        assert_eq!(
+            Query::from(normalized),
+            q("a")
+                .and(q("b"))
+                .and(q("c"))
+                .or(q("d"))
+                .or(q("e").or(q("f")).not(q("g")))
+        );
+
+        assert_eq!(n("a").boolean(), None);
+        assert_eq!(n("a").operands(), [n("a")]);
+        assert_eq!(Query::from(n("a")), q("a"));
+    }
 }
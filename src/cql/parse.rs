@@ -0,0 +1,496 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+use super::{Boolean, Query, Relation, Term};
+
+/// CQLの解析エラー
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} at {}..{}", span.start, span.end)]
+pub struct ParseError {
+    /// エラーの種類
+    pub kind: ParseErrorKind,
+
+    /// エラーが発生した位置 (入力文字列のバイト範囲)
+    pub span: Range<usize>,
+}
+
+/// CQLの解析エラーの種類
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// 入力が空
+    #[error("empty query")]
+    Empty,
+
+    /// 予期しない入力の終わり
+    #[error("unexpected end of query, expected {0}")]
+    UnexpectedEnd(&'static str),
+
+    /// 予期しないトークン
+    #[error("unexpected `{found}`, expected {expected}")]
+    UnexpectedToken {
+        found: String,
+        expected: &'static str,
+    },
+
+    /// 不明な関係演算子
+    #[error("unknown relation `{0}`, expected one of `all`, `any`, `=`")]
+    UnknownRelation(String),
+
+    /// 検索語がない
+    #[error("missing search term")]
+    MissingSearchTerm,
+
+    /// 閉じられていない引用符
+    #[error("unterminated quoted term")]
+    UnterminatedQuote,
+
+    /// 閉じられていない括弧
+    #[error("unclosed parenthesis")]
+    UnclosedParen,
+
+    /// 括弧の入れ子が深すぎる
+    #[error("parentheses nested too deeply")]
+    TooDeep,
+}
+
+/// 括弧の入れ子の上限
+const MAX_DEPTH: usize = 64;
+
+/// CQLフォーマットの文字列を [`Query`] に変換する
+///
+/// 真偽演算子 (`and`, `or`, `not`) は優先順位を持たず, 左から順に結合する.
+/// 検索語は空白で区切り, 空白や括弧などを含む場合は `"` で囲む
+///
+/// # Errors
+///
+/// 文字列がCQLとして正しくないとき, または括弧の入れ子が64段を超えるときエラーを返す
+///
+/// # Example
+///
+/// ```
+/// use crd_api::cql::{self, Query};
+///
+/// let query = cql::parse("question any 本 音楽 and (solution = 0 or ptn-type = 学生)").unwrap();
+/// assert_eq!(
+///     query,
+///     Query::any("question", &["本", "音楽"]).and(
+///         Query::equal("solution", &["0"]).or(Query::equal("ptn-type", &["学生"]))
+///     )
+/// );
+/// ```
+pub fn parse(s: &str) -> Result<Query, ParseError> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        len: s.len(),
+        depth: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err(ParseError {
+            kind: ParseErrorKind::Empty,
+            span: 0..s.len(),
+        });
+    }
+    let query = parser.query()?;
+    match parser.next() {
+        None => Ok(query),
+        Some((token, span)) => Err(ParseError {
+            kind: ParseErrorKind::UnexpectedToken {
+                found: token.to_string(),
+                expected: "`and`, `or`, `not` or end of query",
+            },
+            span,
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Equal,
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn boolean(&self) -> Option<Boolean> {
+        match self {
+            Self::Word(w) if w.eq_ignore_ascii_case("and") => Some(Boolean::And),
+            Self::Word(w) if w.eq_ignore_ascii_case("or") => Some(Boolean::Or),
+            Self::Word(w) if w.eq_ignore_ascii_case("not") => Some(Boolean::Not),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LParen => write!(f, "("),
+            Self::RParen => write!(f, ")"),
+            Self::Equal => write!(f, "="),
+            Self::Word(w) => write!(f, "{w}"),
+            Self::Quoted(q) => write!(f, "\"{q}\""),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push((Token::LParen, start..start + 1)),
+            ')' => tokens.push((Token::RParen, start..start + 1)),
+            '=' => tokens.push((Token::Equal, start..start + 1)),
+            '"' => {
+                let mut term = String::new();
+                let end = loop {
+                    match chars.next() {
+                        Some((i, '"')) => break i + 1,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => term.push(c),
+                            None => break s.len() + 1,
+                        },
+                        Some((_, c)) => term.push(c),
+                        None => break s.len() + 1,
+                    }
+                };
+                if end > s.len() {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnterminatedQuote,
+                        span: start..s.len(),
+                    });
+                }
+                tokens.push((Token::Quoted(term), start..end));
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '=' | '"') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push((Token::Word(s[start..end].to_string()), start..end));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+    len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(Token, Range<usize>)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<(Token, Range<usize>)> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn end(&self, expected: &'static str) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::UnexpectedEnd(expected),
+            span: self.len..self.len,
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, ParseError> {
+        let mut left = self.operand()?;
+        while let Some(boolean) = self.peek().and_then(|(t, _)| t.boolean()) {
+            self.next();
+            let right = self.operand()?;
+            left = Query::ScopedClause {
+                left: left.into(),
+                boolean,
+                right: right.into(),
+            };
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Query, ParseError> {
+        const EXPECTED: &str = "index or `(`";
+        match self.peek() {
+            None => Err(self.end(EXPECTED)),
+            Some((Token::LParen, span)) => {
+                let open = span.clone();
+                if self.depth == MAX_DEPTH {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::TooDeep,
+                        span: open,
+                    });
+                }
+                self.next();
+                self.depth += 1;
+                let query = self.query()?;
+                self.depth -= 1;
+                match self.next() {
+                    Some((Token::RParen, _)) => Ok(query),
+                    None => Err(ParseError {
+                        kind: ParseErrorKind::UnclosedParen,
+                        span: open,
+                    }),
+                    Some((token, span)) => Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedToken {
+                            found: token.to_string(),
+                            expected: "`and`, `or`, `not` or `)`",
+                        },
+                        span,
+                    }),
+                }
+            }
+            Some((token @ Token::Word(_), _)) if token.boolean().is_none() => self.search_clause(),
+            Some((token, span)) => Err(ParseError {
+                kind: ParseErrorKind::UnexpectedToken {
+                    found: token.to_string(),
+                    expected: EXPECTED,
+                },
+                span: span.clone(),
+            }),
+        }
+    }
+
+    fn search_clause(&mut self) -> Result<Query, ParseError> {
+        let Some((Token::Word(index), _)) = self.next() else {
+            unreachable!("search clause must start with an index");
+        };
+        let relation = match self.next() {
+            None => return Err(self.end("relation")),
+            Some((Token::Equal, _)) => Relation::Equal,
+            Some((Token::Word(w), _)) if w.eq_ignore_ascii_case("all") => Relation::All,
+            Some((Token::Word(w), _)) if w.eq_ignore_ascii_case("any") => Relation::Any,
+            Some((token, span)) => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnknownRelation(token.to_string()),
+                    span,
+                })
+            }
+        };
+        let mut search_term = Vec::new();
+        while let Some((token, _)) = self.peek() {
+            match token {
+                Token::Quoted(term) => search_term.push(Term::phrase(term.as_str())),
+                Token::Word(term) if token.boolean().is_none() => {
+                    search_term.push(Term::new(term.as_str()))
+                }
+                _ => break,
+            }
+            self.next();
+        }
+        if search_term.is_empty() {
+            let span = self.peek().map_or(self.len..self.len, |(_, s)| s.clone());
+            return Err(ParseError {
+                kind: ParseErrorKind::MissingSearchTerm,
+                span,
+            });
+        }
+        Ok(Query::SearchClause {
+            index: index.as_str().into(),
+            relation,
+            search_term,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let q = parse("question any 本 音楽 and (solution = 0 or ptn-type = 学生)").unwrap();
+        let expected = Query::any("question", &["本", "音楽"])
+            .and(Query::equal("solution", &["0"]).or(Query::equal("ptn-type", &["学生"])));
+        assert_eq!(q, expected);
+    }
+
+    #[test]
+    fn parse_left_assoc_test() {
+        let q = parse("question any 本 AND answer all 村上 春樹 not note = 備考").unwrap();
+        let expected = Query::any("question", &["本"])
+            .and(Query::all("answer", &["村上", "春樹"]))
+            .not(Query::equal("note", &["備考"]));
+        assert_eq!(q, expected);
+    }
+
+    #[test]
+    fn parse_quoted_test() {
+        let q = parse(r#"anywhere="rust and \"go\"" (x)"#);
+        assert_eq!(
+            q.unwrap_err(),
+            ParseError {
+                kind: ParseErrorKind::UnexpectedToken {
+                    found: "(".to_string(),
+                    expected: "`and`, `or`, `not` or end of query",
+                },
+                span: 27..28,
+            }
+        );
+        let q = parse(r#"anywhere="rust and \"go\"""#).unwrap();
+        assert_eq!(q, Query::equal("anywhere", &[r#"rust and "go""#]));
+    }
+
+    #[test]
+    fn parse_round_trip_test() {
+        let queries = [
+            Query::new(&["rust"]),
+            Query::any("question", &["本", "音楽"])
+                .and(Query::equal("solution", &["resolved"]))
+                .or(Query::equal("ptn-type", &["学生"])),
+            Query::any("question", &["本"]).and(
+                Query::all("answer", &["村上春樹"])
+                    .not(Query::equal("ndc", &["913"]).or(Query::equal("keyword", &["小説"]))),
+            ),
+        ];
+        for query in queries {
+            assert_eq!(parse(&query.to_string()).unwrap(), query);
+        }
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let cases: [(&str, ParseErrorKind, Range<usize>); 8] = [
+            ("", ParseErrorKind::Empty, 0..0),
+            ("question", ParseErrorKind::UnexpectedEnd("relation"), 8..8),
+            (
+                "question foo rust",
+                ParseErrorKind::UnknownRelation("foo".to_string()),
+                9..12,
+            ),
+            ("question any", ParseErrorKind::MissingSearchTerm, 12..12),
+            (
+                "question any and answer any x",
+                ParseErrorKind::MissingSearchTerm,
+                13..16,
+            ),
+            (
+                "question any \"rust",
+                ParseErrorKind::UnterminatedQuote,
+                13..18,
+            ),
+            ("(question any rust", ParseErrorKind::UnclosedParen, 0..1),
+            (
+                "question any rust and",
+                ParseErrorKind::UnexpectedEnd("index or `(`"),
+                21..21,
+            ),
+        ];
+        for (s, kind, span) in cases {
+            assert_eq!(parse(s).unwrap_err(), ParseError { kind, span }, "{s}");
+        }
+    }
+
+    #[test]
+    fn parse_depth_test() {
+        let nested = |n: usize| format!("{}anywhere = rust{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!(parse(&nested(MAX_DEPTH)).unwrap(), Query::new(&["rust"]));
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)).unwrap_err(),
+            ParseError {
+                kind: ParseErrorKind::TooDeep,
+                span: MAX_DEPTH..MAX_DEPTH + 1,
+            }
+        );
+        assert_eq!(
+            parse(&"(".repeat(50_000)).unwrap_err(),
+            ParseError {
+                kind: ParseErrorKind::TooDeep,
+                span: MAX_DEPTH..MAX_DEPTH + 1,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_term_test() {
+        let q = parse(r#"question any rust "村上 春樹" "and" "a\\b""#).unwrap();
+        assert_eq!(
+            q,
+            Query::SearchClause {
+                index: "question".into(),
+                relation: Relation::Any,
+                search_term: vec![
+                    Term::word("rust").unwrap(),
+                    Term::phrase("村上 春樹"),
+                    Term::phrase("and"),
+                    Term::phrase(r"a\b"),
+                ],
+            }
+        );
+        assert_eq!(
+            q.to_string(),
+            r#"question any rust "村上 春樹" "and" "a\\b""#
+        );
+    }
+
+    mod prop {
+        use proptest::prelude::*;
+
+        use super::super::*;
+
+        fn term() -> impl Strategy<Value = Term> {
+            prop_oneof![
+                "[a-z0-9ぁ-ん一-龠-]{1,8}".prop_filter_map("not a word", |s| Term::word(s).ok()),
+                "[a-zA-Z0-9ぁ-ん()=\"\\\\ \u{3000}\t-]{0,8}".prop_map(Term::new),
+                "[a-zA-Z0-9ぁ-ん()=\"\\\\ ]{0,8}".prop_map(Term::phrase),
+                prop::sample::select(&["and", "OR", "Not", "all", "any", "="][..])
+                    .prop_map(Term::new),
+            ]
+        }
+
+        fn search_clause() -> impl Strategy<Value = Query> {
+            (
+                prop::sample::select(&["anywhere", "question", "ndc", "lib-type"][..]),
+                prop::sample::select(&[Relation::All, Relation::Any, Relation::Equal][..]),
+                prop::collection::vec(term(), 1..4),
+            )
+                .prop_map(|(index, relation, search_term)| Query::SearchClause {
+                    index: index.into(),
+                    relation,
+                    search_term,
+                })
+        }
+
+        fn query() -> impl Strategy<Value = Query> {
+            search_clause().prop_recursive(4, 16, 2, |inner| {
+                (
+                    inner.clone(),
+                    prop::sample::select(&[Boolean::And, Boolean::Or, Boolean::Not][..]),
+                    inner,
+                )
+                    .prop_map(|(left, boolean, right)| Query::ScopedClause {
+                        left: left.into(),
+                        boolean,
+                        right: right.into(),
+                    })
+            })
+        }
+
+        proptest! {
+            #[test]
+            fn round_trip(query in query()) {
+                // 同じ演算子の入れ子は括弧を省略するため, 正規形で比較する
+                let parsed = parse(&query.to_string()).unwrap();
+                let normalized = query.clone().normalize();
+                prop_assert_eq!(parsed.clone().normalize(), normalized.clone());
+                prop_assert_eq!(normalized.to_string(), query.to_string());
+                // 解析結果は左から順に結合した木になる
+                prop_assert_eq!(Query::from(normalized), parsed);
+            }
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use super::{CollectionIndex, Index, ManualIndex, Query, ReferenceIndex, Relation};
+use crate::response::{Class, Collection, Manual, Reference};
+
+/// 類似検索の元にできる事例
+///
+/// [`Reference`], [`Manual`], [`Collection`] に実装されている
+pub trait SimilarSource {
+    /// システムID
+    fn sys_id(&self) -> &str;
+
+    /// キーワード
+    fn keywords(&self) -> &[String];
+
+    /// 分類
+    fn classes(&self) -> &[Class];
+
+    /// 語を抽出する項目とその値
+    fn text(&self) -> (Index, &str);
+}
+
+impl SimilarSource for Reference {
+    fn sys_id(&self) -> &str {
+        &self.system.sys_id
+    }
+
+    fn keywords(&self) -> &[String] {
+        self.keyword.as_deref().unwrap_or_default()
+    }
+
+    fn classes(&self) -> &[Class] {
+        self.class.as_deref().unwrap_or_default()
+    }
+
+    fn text(&self) -> (Index, &str) {
+        (ReferenceIndex::Question.into(), &self.question)
+    }
+}
+
+impl SimilarSource for Manual {
+    fn sys_id(&self) -> &str {
+        &self.system.sys_id
+    }
+
+    fn keywords(&self) -> &[String] {
+        self.keyword.as_deref().unwrap_or_default()
+    }
+
+    fn classes(&self) -> &[Class] {
+        self.class.as_deref().unwrap_or_default()
+    }
+
+    fn text(&self) -> (Index, &str) {
+        (ManualIndex::Theme.into(), &self.theme)
+    }
+}
+
+impl SimilarSource for Collection {
+    fn sys_id(&self) -> &str {
+        &self.system.sys_id
+    }
+
+    fn keywords(&self) -> &[String] {
+        self.keyword.as_deref().unwrap_or_default()
+    }
+
+    fn classes(&self) -> &[Class] {
+        self.class.as_deref().unwrap_or_default()
+    }
+
+    fn text(&self) -> (Index, &str) {
+        (CollectionIndex::Outline.into(), &self.outline)
+    }
+}
+
+/// 類似検索のクエリーの設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimilarOptions {
+    /// 使用するキーワードの最大数 (デフォルト: 5)
+    pub max_keywords: usize,
+
+    /// キーワードの関係 (デフォルト: [`Relation::Any`])
+    pub keyword_relation: Relation,
+
+    /// 使用する分類の最大数 (デフォルト: 2)
+    pub max_classes: usize,
+
+    /// NDCの前方一致に使用する桁数 (1〜3, デフォルト: 3)
+    pub ndc_digits: usize,
+
+    /// 質問などから抽出する語の最大数 (デフォルト: 3)
+    pub max_terms: usize,
+
+    /// 抽出した語の関係 (デフォルト: [`Relation::Any`])
+    pub term_relation: Relation,
+
+    /// 元の事例を `sys-id` で除外する (デフォルト: `true`)
+    pub exclude_source: bool,
+}
+
+impl Default for SimilarOptions {
+    fn default() -> Self {
+        Self {
+            max_keywords: 5,
+            keyword_relation: Relation::Any,
+            max_classes: 2,
+            ndc_digits: 3,
+            max_terms: 3,
+            term_relation: Relation::Any,
+            exclude_source: true,
+        }
+    }
+}
+
+impl Query {
+    /// 事例に類似する事例を検索するクエリー
+    ///
+    /// キーワード, NDCの前方一致, 質問などから抽出した語の検索句をOR条件で結合する.
+    /// 設定は [`SimilarOptions::default`] を使用する
+    ///
+    /// 検索句を作成できない場合は `None`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use crd_api::{
+    ///     cql::Query,
+    ///     request::{Request, SearchType},
+    /// };
+    ///
+    /// # async fn run(client: crd_api::client::Client) -> anyhow::Result<()> {
+    /// let result = client.search(&Request::new("村上春樹")).await?;
+    /// if let Some(reference) = result.filter_reference().next() {
+    ///     let query = Query::similar_to(reference).unwrap();
+    ///     let request = crd_api::builder()
+    ///         .search_type(SearchType::Reference)
+    ///         .query(query.to_string())
+    ///         .build()?;
+    ///     let similar = client.search(&request).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn similar_to(source: &impl SimilarSource) -> Option<Self> {
+        Self::similar_to_with(source, &SimilarOptions::default())
+    }
+
+    /// 設定を指定して事例に類似する事例を検索するクエリーを作成する
+    pub fn similar_to_with(source: &impl SimilarSource, options: &SimilarOptions) -> Option<Self> {
+        let clause = |index: Index, relation: &Relation, terms: &[&str]| {
+            (!terms.is_empty()).then(|| match relation {
+                Relation::All => Self::all(index, terms),
+                Relation::Any => Self::any(index, terms),
+                Relation::Equal => Self::equal(index, terms),
+            })
+        };
+
+        let mut keywords: Vec<&str> = Vec::new();
+        for keyword in source.keywords() {
+            if keywords.len() < options.max_keywords && !keywords.contains(&keyword.as_str()) {
+                keywords.push(keyword);
+            }
+        }
+
+        let mut prefixes: Vec<String> = Vec::new();
+        for ndc in source.classes().iter().filter_map(Class::ndc) {
+            let prefix = match options.ndc_digits {
+                0 | 1 => Some(ndc.class()),
+                2 => ndc.division(),
+                _ => ndc.section(),
+            };
+            let prefix = prefix.unwrap_or(ndc).to_string();
+            if prefixes.len() < options.max_classes && !prefixes.contains(&prefix) {
+                prefixes.push(prefix);
+            }
+        }
+        let prefixes: Vec<&str> = prefixes.iter().map(String::as_str).collect();
+
+        let (text_index, text) = source.text();
+        let terms: Vec<&str> = salient_terms(text)
+            .into_iter()
+            .filter(|t| !keywords.contains(t))
+            .take(options.max_terms)
+            .collect();
+
+        let query = [
+            clause("keyword".into(), &options.keyword_relation, &keywords),
+            clause("ndc".into(), &Relation::Any, &prefixes),
+            clause(text_index, &options.term_relation, &terms),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(Self::or)?;
+        if options.exclude_source {
+            Some(query.not(Self::equal("sys-id", &[source.sys_id()])))
+        } else {
+            Some(query)
+        }
+    }
+}
+
+/// 抽出の対象外とする語
+const STOP_WORDS: &[&str] = &[
+    "資料", "情報", "方法", "場合", "関係", "内容", "事例", "参考", "紹介",
+];
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Script {
+    Kanji,
+    Katakana,
+    Alphanumeric,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Self {
+        match c {
+            '々' | '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => Self::Kanji,
+            'ー' | '\u{30A1}'..='\u{30FA}' => Self::Katakana,
+            c if c.is_ascii_alphanumeric() => Self::Alphanumeric,
+            '\u{FF10}'..='\u{FF19}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' => {
+                Self::Alphanumeric
+            }
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 文から特徴的な語を抽出する
+///
+/// 漢字, カタカナ, 英数字の連続を語とみなし, 1文字の語と [`STOP_WORDS`] を除いて
+/// 出現回数の多い順 (同数の場合は出現順) に並べる
+fn salient_terms(text: &str) -> Vec<&str> {
+    let mut runs: Vec<&str> = Vec::new();
+    let mut current: Option<(usize, Script)> = None;
+    for (i, c) in text.char_indices().chain([(text.len(), '\0')]) {
+        let script = Script::of(c);
+        if let Some((begin, s)) = current {
+            if s == script {
+                continue;
+            }
+            if s != Script::Other {
+                runs.push(&text[begin..i]);
+            }
+        }
+        current = Some((i, script));
+    }
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for run in runs {
+        if run.chars().count() < 2 || STOP_WORDS.contains(&run) {
+            continue;
+        }
+        match positions.get(run) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                positions.insert(run, counts.len());
+                counts.push((run, 1));
+            }
+        }
+    }
+    // 安定ソートのため同数の場合は出現順になる
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts.into_iter().map(|(term, _)| term).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mock::Corpus,
+        request::{Request, SearchType},
+        response::ResultItem,
+    };
+
+    fn reference(corpus: &Corpus, i: usize) -> Reference {
+        match &corpus.items()[i] {
+            ResultItem::Reference(r) => r.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn salient_terms_test() {
+        assert_eq!(
+            salient_terms("村上春樹の作品で音楽が登場する本を知りたい。"),
+            ["村上春樹", "作品", "音楽", "登場"]
+        );
+        assert_eq!(
+            salient_terms("Rustというプログラミング言語の入門書はあるか。"),
+            ["Rust", "プログラミング", "言語", "入門書"]
+        );
+        assert_eq!(
+            salient_terms("地図の資料と古い地図を探している。"),
+            ["地図"]
+        );
+    }
+
+    #[test]
+    fn similar_to_test() {
+        let corpus = Corpus::fixtures();
+        let q = Query::similar_to(&reference(&corpus, 1)).unwrap();
+        assert_eq!(
+            q.to_string(),
+            "( keyword any 村上春樹 音楽 小説 or ndc any 910 760 or question any 作品 登場 ) \
+             not sys-id = 1000000002"
+        );
+
+        let options = SimilarOptions {
+            max_keywords: 1,
+            keyword_relation: Relation::All,
+            max_classes: 1,
+            ndc_digits: 1,
+            max_terms: 0,
+            exclude_source: false,
+            ..Default::default()
+        };
+        let q = Query::similar_to_with(&reference(&corpus, 1), &options).unwrap();
+        assert_eq!(q.to_string(), "keyword all 村上春樹 or ndc any 9");
+
+        let options = SimilarOptions {
+            max_keywords: 0,
+            max_classes: 0,
+            max_terms: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            Query::similar_to_with(&reference(&corpus, 1), &options),
+            None
+        );
+    }
+
+    #[test]
+    fn search_test() {
+        let mut corpus = Corpus::fixtures();
+        let mut similar = reference(&corpus, 2);
+        similar.system.sys_id = "1000000005".to_string();
+        similar.question = "江戸時代の港の様子がわかる絵図".to_string();
+        corpus.push(ResultItem::Reference(similar));
+
+        let q = Query::similar_to(&reference(&corpus, 2)).unwrap();
+        let request = Request {
+            search_type: Some(SearchType::Reference),
+            query: Some(q.to_string()),
+            ..Default::default()
+        };
+        let result = corpus.search(&request).unwrap();
+        let ids: Vec<_> = result.iter().map(ResultItem::id).collect();
+        assert_eq!(ids, ["1000000005"]);
+
+        let collection = match &corpus.items()[6] {
+            ResultItem::Collection(c) => c.clone(),
+            _ => unreachable!(),
+        };
+        let q = Query::similar_to(&collection).unwrap();
+        assert!(q.validate_for(SearchType::Collection).is_ok());
+    }
+}
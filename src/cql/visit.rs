@@ -0,0 +1,431 @@
+use super::{Boolean, Index, Query, Relation, Term};
+use crate::request::SearchType;
+
+/// [`Query`] を走査する
+///
+/// 既定の実装は全ての検索句を左から順に走査する. 必要なメソッドだけを上書きすればよい
+///
+/// # Example
+///
+/// ```
+/// use crd_api::cql::{Index, Query, Relation, Term, Visitor};
+///
+/// /// 使用されている項目を集める
+/// struct Indexes(Vec<String>);
+///
+/// impl Visitor for Indexes {
+///     fn visit_search_clause(&mut self, index: &Index, _: &Relation, _: &[Term]) {
+///         self.0.push(index.to_string());
+///     }
+/// }
+///
+/// let query: Query = "question any 本 and (answer = 音楽 or keyword = 小説)".parse().unwrap();
+/// let mut indexes = Indexes(Vec::new());
+/// indexes.visit_query(&query);
+/// assert_eq!(indexes.0, ["question", "answer", "keyword"]);
+/// ```
+pub trait Visitor {
+    /// クエリーを走査する
+    fn visit_query(&mut self, query: &Query) {
+        walk_query(self, query);
+    }
+
+    /// 単一の検索句を走査する
+    fn visit_search_clause(&mut self, index: &Index, relation: &Relation, search_term: &[Term]) {
+        let _ = (index, relation, search_term);
+    }
+
+    /// 複数の検索句の結合を走査する
+    fn visit_scoped_clause(&mut self, left: &Query, boolean: Boolean, right: &Query) {
+        walk_scoped_clause(self, left, boolean, right);
+    }
+}
+
+/// [`Visitor::visit_query`] の既定の実装
+pub fn walk_query<V: Visitor + ?Sized>(visitor: &mut V, query: &Query) {
+    match query {
+        Query::SearchClause {
+            index,
+            relation,
+            search_term,
+        } => visitor.visit_search_clause(index, relation, search_term),
+        Query::ScopedClause {
+            left,
+            boolean,
+            right,
+        } => visitor.visit_scoped_clause(left, *boolean, right),
+    }
+}
+
+/// [`Visitor::visit_scoped_clause`] の既定の実装
+pub fn walk_scoped_clause<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    left: &Query,
+    _boolean: Boolean,
+    right: &Query,
+) {
+    visitor.visit_query(left);
+    visitor.visit_query(right);
+}
+
+/// [`Query`] を書き換える
+///
+/// `None` を返した検索句はクエリーから取り除く. 結合の一方が取り除かれた場合は残った方を返す.
+/// ただし `not` の左側が取り除かれた場合は結合全体を取り除く
+///
+/// # Example
+///
+/// ```
+/// use crd_api::cql::{Fold, MapIndex, Query};
+///
+/// // `anywhere` を質問と回答の検索に置き換える
+/// let mut fold = MapIndex::new(|index| match index.as_str() {
+///     "anywhere" => vec!["question".into(), "answer".into()],
+///     _ => vec![index.clone()],
+/// });
+/// let query = fold.fold_query(Query::new(&["rust"])).unwrap();
+/// assert_eq!(query.to_string(), "question = rust or answer = rust");
+/// ```
+pub trait Fold {
+    /// クエリーを書き換える
+    fn fold_query(&mut self, query: Query) -> Option<Query> {
+        fold_query(self, query)
+    }
+
+    /// 単一の検索句を書き換える
+    fn fold_search_clause(
+        &mut self,
+        index: Index,
+        relation: Relation,
+        search_term: Vec<Term>,
+    ) -> Option<Query> {
+        Some(Query::SearchClause {
+            index,
+            relation,
+            search_term,
+        })
+    }
+
+    /// 複数の検索句の結合を書き換える
+    fn fold_scoped_clause(&mut self, left: Query, boolean: Boolean, right: Query) -> Option<Query> {
+        fold_scoped_clause(self, left, boolean, right)
+    }
+}
+
+/// [`Fold::fold_query`] の既定の実装
+pub fn fold_query<F: Fold + ?Sized>(folder: &mut F, query: Query) -> Option<Query> {
+    match query {
+        Query::SearchClause {
+            index,
+            relation,
+            search_term,
+        } => folder.fold_search_clause(index, relation, search_term),
+        Query::ScopedClause {
+            left,
+            boolean,
+            right,
+        } => folder.fold_scoped_clause(*left, boolean, *right),
+    }
+}
+
+/// [`Fold::fold_scoped_clause`] の既定の実装
+pub fn fold_scoped_clause<F: Fold + ?Sized>(
+    folder: &mut F,
+    left: Query,
+    boolean: Boolean,
+    right: Query,
+) -> Option<Query> {
+    let left = folder.fold_query(left);
+    let right = folder.fold_query(right);
+    match (left, right) {
+        (Some(left), Some(right)) => Some(Query::ScopedClause {
+            left: left.into(),
+            boolean,
+            right: right.into(),
+        }),
+        (None, _) if boolean == Boolean::Not => None,
+        (left, right) => left.or(right),
+    }
+}
+
+/// 検索語を書き換える
+///
+/// 関数は項目と検索語を受け取り, 置き換える検索語を返す.
+/// 複数の検索語を返した場合は同義語として扱い, いずれかに一致するように検索句を組み立てる.
+/// 空の場合はその検索語を取り除き, 検索語がなくなった検索句は取り除く
+///
+/// # Example
+///
+/// ```
+/// use crd_api::cql::{Fold, MapTerms, Query};
+///
+/// let mut synonyms = MapTerms::new(|_, term| match term.as_str() {
+///     "猫" => vec!["猫".into(), "ネコ".into()],
+///     _ => vec![term],
+/// });
+/// let query = Query::all("question", &["猫", "飼い方"]);
+/// assert_eq!(
+///     synonyms.fold_query(query).unwrap().to_string(),
+///     "question all 飼い方 and question any 猫 ネコ"
+/// );
+/// ```
+pub struct MapTerms<F>(F);
+
+impl<F: FnMut(&Index, Term) -> Vec<Term>> MapTerms<F> {
+    /// 検索語を書き換える関数から作成する
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl<F: FnMut(&Index, Term) -> Vec<Term>> Fold for MapTerms<F> {
+    fn fold_search_clause(
+        &mut self,
+        index: Index,
+        relation: Relation,
+        search_term: Vec<Term>,
+    ) -> Option<Query> {
+        let mut mapped: Vec<Vec<Term>> = search_term
+            .into_iter()
+            .map(|term| (self.0)(&index, term))
+            .filter(|terms| !terms.is_empty())
+            .collect();
+        let clause = |relation, search_term| Query::SearchClause {
+            index: index.clone(),
+            relation,
+            search_term,
+        };
+        match relation {
+            _ if mapped.is_empty() => None,
+            Relation::Any => Some(clause(Relation::Any, mapped.concat())),
+            // 同義語の組ごとにいずれかに一致する検索句を作り, 全てを満たすように結合する
+            Relation::All => {
+                let (singles, synonyms): (Vec<_>, Vec<_>) =
+                    mapped.into_iter().partition(|terms| terms.len() == 1);
+                (!singles.is_empty())
+                    .then(|| clause(Relation::All, singles.concat()))
+                    .into_iter()
+                    .chain(synonyms.into_iter().map(|s| clause(Relation::Any, s)))
+                    .reduce(Query::and)
+            }
+            // 完全一致は語の並びで一致するため, 同義語は単独の検索語の場合のみ展開する
+            Relation::Equal if mapped.len() == 1 => mapped
+                .remove(0)
+                .into_iter()
+                .map(|term| clause(Relation::Equal, vec![term]))
+                .reduce(Query::or),
+            Relation::Equal => Some(clause(
+                Relation::Equal,
+                mapped
+                    .into_iter()
+                    .map(|mut terms| terms.remove(0))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// 項目を書き換える
+///
+/// 関数は項目を受け取り, 置き換える項目を返す.
+/// 複数の項目を返した場合はいずれかの項目で一致するように検索句をOR条件で結合し,
+/// 空の場合は検索句を取り除く
+pub struct MapIndex<F>(F);
+
+impl<F: FnMut(&Index) -> Vec<Index>> MapIndex<F> {
+    /// 項目を書き換える関数から作成する
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+
+impl MapIndex<Box<dyn FnMut(&Index) -> Vec<Index>>> {
+    /// 項目名を変更する
+    pub fn rename(from: impl Into<Index>, to: impl Into<Index>) -> Self {
+        let (from, to) = (from.into(), to.into());
+        Self(Box::new(move |index| {
+            vec![if *index == from {
+                to.clone()
+            } else {
+                index.clone()
+            }]
+        }))
+    }
+}
+
+impl<F: FnMut(&Index) -> Vec<Index>> Fold for MapIndex<F> {
+    fn fold_search_clause(
+        &mut self,
+        index: Index,
+        relation: Relation,
+        search_term: Vec<Term>,
+    ) -> Option<Query> {
+        (self.0)(&index)
+            .into_iter()
+            .map(|index| Query::SearchClause {
+                index,
+                relation: relation.clone(),
+                search_term: search_term.clone(),
+            })
+            .reduce(Query::or)
+    }
+}
+
+/// 条件を満たさない検索句を取り除く
+///
+/// 全ての検索句が取り除かれた場合, 結果は `None` となる
+///
+/// # Example
+///
+/// ```
+/// use crd_api::{
+///     cql::{Fold, Prune, Query},
+///     request::SearchType,
+/// };
+///
+/// let query: Query = "theme any 地図 or question any 地図".parse().unwrap();
+/// let query = Prune::unsupported(SearchType::Manual).fold_query(query).unwrap();
+/// assert_eq!(query.to_string(), "theme any 地図");
+/// ```
+pub struct Prune<F>(F);
+
+impl<F: FnMut(&Index, &Relation, &[Term]) -> bool> Prune<F> {
+    /// 検索句を残す条件から作成する
+    pub fn new(keep: F) -> Self {
+        Self(keep)
+    }
+}
+
+impl Prune<Box<dyn FnMut(&Index, &Relation, &[Term]) -> bool>> {
+    /// 指定した検索区分で使用できない項目の検索句を取り除く
+    pub fn unsupported(search_type: SearchType) -> Self {
+        Self(Box::new(move |index, _, _| index.is_valid_for(search_type)))
+    }
+}
+
+impl<F: FnMut(&Index, &Relation, &[Term]) -> bool> Fold for Prune<F> {
+    fn fold_search_clause(
+        &mut self,
+        index: Index,
+        relation: Relation,
+        search_term: Vec<Term>,
+    ) -> Option<Query> {
+        (self.0)(&index, &relation, &search_term).then_some(Query::SearchClause {
+            index,
+            relation,
+            search_term,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        clauses: usize,
+        booleans: Vec<Boolean>,
+    }
+
+    impl Visitor for Counter {
+        fn visit_search_clause(&mut self, _: &Index, _: &Relation, _: &[Term]) {
+            self.clauses += 1;
+        }
+
+        fn visit_scoped_clause(&mut self, left: &Query, boolean: Boolean, right: &Query) {
+            self.booleans.push(boolean);
+            walk_scoped_clause(self, left, boolean, right);
+        }
+    }
+
+    fn q(s: &str) -> Query {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn visitor_test() {
+        let mut counter = Counter::default();
+        counter.visit_query(&q("a = 1 and (b = 2 or c = 3) not d = 4"));
+        assert_eq!(counter.clauses, 4);
+        assert_eq!(counter.booleans, [Boolean::Not, Boolean::And, Boolean::Or]);
+    }
+
+    #[test]
+    fn default_fold_test() {
+        struct Identity;
+        impl Fold for Identity {}
+
+        let query = q("a = 1 and (b = 2 or c = 3) not d = 4");
+        assert_eq!(Identity.fold_query(query.clone()), Some(query));
+    }
+
+    #[test]
+    fn map_terms_test() {
+        let mut fold = MapTerms::new(|index: &Index, term: Term| match term.as_str() {
+            "猫" if index.as_str() != "ndc" => vec![term, "ネコ".into()],
+            "の" => vec![],
+            _ => vec![term],
+        });
+        let cases = [
+            ("question any 猫 犬", Some("question any 猫 ネコ 犬")),
+            ("question all の", None),
+            (
+                "question all 猫 飼い方 の",
+                Some("question all 飼い方 and question any 猫 ネコ"),
+            ),
+            ("keyword = 猫", Some("keyword = 猫 or keyword = ネコ")),
+            ("keyword = 黒い 猫", Some("keyword = 黒い 猫")),
+            ("keyword = 猫 の 本", Some("keyword = 猫 本")),
+            ("ndc = 猫", Some("ndc = 猫")),
+            ("question any の not answer any 猫", None),
+            ("question any 犬 not answer any の", Some("question any 犬")),
+        ];
+        for (query, expected) in cases {
+            let folded = fold.fold_query(q(query));
+            assert_eq!(
+                folded.map(|q| q.to_string()).as_deref(),
+                expected,
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn map_index_test() {
+        let mut fold = MapIndex::new(|index: &Index| match index.as_str() {
+            "anywhere" => vec!["question".into(), "answer".into()],
+            "note" => vec![],
+            _ => vec![index.clone()],
+        });
+        let query = fold
+            .fold_query(q("anywhere any 本 and note = x and ndc = 91"))
+            .unwrap();
+        assert_eq!(
+            query.to_string(),
+            "( question any 本 or answer any 本 ) and ndc = 91"
+        );
+
+        let query = MapIndex::rename("anywhere", "keyword")
+            .fold_query(q("anywhere = 本 or question = 本"))
+            .unwrap();
+        assert_eq!(query.to_string(), "keyword = 本 or question = 本");
+    }
+
+    #[test]
+    fn prune_test() {
+        let query = q("question any 本 and (theme = 地図 or ndc = 29) not lib-type = 11");
+        let pruned = Prune::unsupported(SearchType::Reference).fold_query(query.clone());
+        assert_eq!(pruned, Some(q("question any 本 and ndc = 29")));
+
+        let pruned = Prune::unsupported(SearchType::Profile).fold_query(query);
+        assert_eq!(pruned, None);
+
+        let mut fold =
+            Prune::new(|_: &Index, relation: &Relation, _: &[Term]| *relation != Relation::Equal);
+        assert_eq!(
+            fold.fold_query(q("question any 本 or ndc = 29")),
+            Some(q("question any 本"))
+        );
+    }
+}
@@ -1,15 +1,63 @@
-use std::fmt::Display;
+use std::fmt::{Display, Write};
 
-use quick_xml::DeError;
+use quick_xml::{escape::escape, DeError};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
-#[error(transparent)]
 pub enum Error {
+    #[error(transparent)]
     Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
     De(#[from] DeError),
+
+    #[error(transparent)]
     Api(#[from] ApiErrors),
+
+    /// カセットの読み書きに失敗した
+    #[error(transparent)]
+    Cassette(#[from] crate::client::CassetteError),
+
+    /// リクエスト頻度の制限の設定が正しくない
+    #[error(transparent)]
+    RateLimit(#[from] crate::client::InvalidRateLimitError),
+
+    /// 成功以外のHTTPステータスが返却され, レスポンスを解析できなかった
+    #[error("unexpected HTTP status: {0}")]
+    Status(reqwest::StatusCode),
+
+    /// 再試行しても成功しなかった
+    ///
+    /// メッセージに最後の試行のエラーは含めない. [`source`](std::error::Error::source) で取得できる
+    #[error("failed after {attempts} attempts")]
+    Retry {
+        /// 試行回数
+        attempts: u32,
+
+        /// 最後の試行のエラー
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// 試行回数
+    ///
+    /// 再試行を行わなかった場合は `1`
+    pub fn attempts(&self) -> u32 {
+        match self {
+            Self::Retry { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+
+    /// 再試行によるエラーの場合, 最後の試行のエラーを返す
+    pub fn last_error(&self) -> &Error {
+        match self {
+            Self::Retry { source, .. } => source,
+            e => e,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -29,9 +77,48 @@ struct ErrList {
 pub struct ApiErrors(Vec<ApiError>);
 
 impl ApiErrors {
+    /// エラー情報のリストから作成する
+    pub fn new(errors: Vec<ApiError>) -> Self {
+        Self(errors)
+    }
+
     pub fn from_xml(s: &str) -> Result<Self, quick_xml::DeError> {
         quick_xml::de::from_str(s)
     }
+
+    /// APIの返却結果と同じ形式のxmlに変換する
+    pub fn to_xml(&self) -> String {
+        let mut s = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?><result_set><results_cd>1</results_cd><err_list>"#,
+        );
+        for e in &self.0 {
+            write!(
+                s,
+                "<err_item><err_code>{}</err_code><err_fld>{}</err_fld><err_msg>{}</err_msg></err_item>",
+                escape(&e.err_code),
+                escape(&e.err_fld),
+                escape(&e.err_msg)
+            )
+            .unwrap();
+        }
+        s.push_str("</err_list></result_set>");
+        s
+    }
+
+    /// エラー情報のイテレータを返す
+    pub fn iter(&self) -> impl Iterator<Item = &ApiError> {
+        self.0.iter()
+    }
+
+    /// 全てのエラーが再試行で解消する可能性があるなら [`true`] を返す
+    pub fn is_retryable(&self) -> bool {
+        !self.0.is_empty() && self.0.iter().all(|e| e.code().is_retryable())
+    }
+
+    /// リクエストの誤りによるエラーを含むなら [`true`] を返す
+    pub fn is_client_error(&self) -> bool {
+        self.0.iter().any(|e| e.code().is_client_error())
+    }
 }
 
 impl Display for ApiErrors {
@@ -73,6 +160,139 @@ pub struct ApiError {
     pub err_msg: String,
 }
 
+impl ApiError {
+    /// エラーコード
+    pub fn code(&self) -> ApiErrorCode {
+        self.err_code.as_str().into()
+    }
+
+    /// エラーが発生したフィールド
+    ///
+    /// [`err_fld`](Self::err_fld) が空の場合は [`None`]
+    pub fn field(&self) -> Option<&str> {
+        Some(self.err_fld.as_str()).filter(|s| !s.is_empty())
+    }
+}
+
+/// エラーコード
+///
+/// 参照: <https://crd.ndl.go.jp/jp/help/general/api_spec_2.html#errcode>
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(from = "String", into = "String")]
+pub enum ApiErrorCode {
+    /// `0101`: 検索必須項目が指定されていない
+    MissingRequired,
+
+    /// `0201`: 数値を指定する項目に数値以外が指定されている
+    NotNumeric,
+
+    /// `0301`: 日付の形式が正しくない
+    InvalidDate,
+
+    /// `0401`: 指定できる桁数を超えている
+    TooLong,
+
+    /// `0501`: 指定できる範囲外の値が指定されている
+    OutOfRange,
+
+    /// `0502`: 検索条件 (CQL) の書式が正しくない
+    InvalidQuery,
+
+    /// `0503`: 使用できない値が指定されている
+    InvalidValue,
+
+    /// `0601`: 検索結果が上限件数を超えている
+    TooManyResults,
+
+    /// `0901`: システムエラー
+    SystemError,
+
+    /// `0902`: サービスが一時的に利用できない
+    ServiceUnavailable,
+
+    /// 上記以外のエラーコード
+    Unknown(String),
+}
+
+impl ApiErrorCode {
+    /// エラーコードの文字列
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::MissingRequired => "0101",
+            Self::NotNumeric => "0201",
+            Self::InvalidDate => "0301",
+            Self::TooLong => "0401",
+            Self::OutOfRange => "0501",
+            Self::InvalidQuery => "0502",
+            Self::InvalidValue => "0503",
+            Self::TooManyResults => "0601",
+            Self::SystemError => "0901",
+            Self::ServiceUnavailable => "0902",
+            Self::Unknown(code) => code,
+        }
+    }
+
+    /// 同じリクエストを再試行すると解消する可能性があるなら [`true`] を返す
+    ///
+    /// サーバー側の問題によるエラー (`0901`, `0902`) が該当する
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::SystemError | Self::ServiceUnavailable)
+    }
+
+    /// リクエストの誤りによるエラーなら [`true`] を返す
+    ///
+    /// リクエストを修正しない限り再試行しても解消しない
+    pub fn is_client_error(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingRequired
+                | Self::NotNumeric
+                | Self::InvalidDate
+                | Self::TooLong
+                | Self::OutOfRange
+                | Self::InvalidQuery
+                | Self::InvalidValue
+                | Self::TooManyResults
+        )
+    }
+}
+
+impl Display for ApiErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<&str> for ApiErrorCode {
+    fn from(value: &str) -> Self {
+        match value {
+            "0101" => Self::MissingRequired,
+            "0201" => Self::NotNumeric,
+            "0301" => Self::InvalidDate,
+            "0401" => Self::TooLong,
+            "0501" => Self::OutOfRange,
+            "0502" => Self::InvalidQuery,
+            "0503" => Self::InvalidValue,
+            "0601" => Self::TooManyResults,
+            "0901" => Self::SystemError,
+            "0902" => Self::ServiceUnavailable,
+            _ => Self::Unknown(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for ApiErrorCode {
+    fn from(value: String) -> Self {
+        value.as_str().into()
+    }
+}
+
+impl From<ApiErrorCode> for String {
+    fn from(value: ApiErrorCode) -> Self {
+        value.as_str().to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use quick_xml::de::from_str;
@@ -100,6 +320,26 @@ mod tests {
         assert_eq!(e.0.len(), 2);
         assert_eq!(e.0[0].err_code, "0101");
         assert_eq!(e.0[1].err_code, "0503");
+        assert_eq!(e.0[1].code(), ApiErrorCode::InvalidValue);
+        assert_eq!(e.0[1].field(), Some("ndc"));
+        assert!(e.is_client_error());
+        assert!(!e.is_retryable());
+        assert_eq!(ApiErrors::from_xml(&e.to_xml()).unwrap(), e);
+    }
+
+    #[test]
+    fn api_error_code_test() {
+        assert_eq!(ApiErrorCode::from("0101"), ApiErrorCode::MissingRequired);
+        assert_eq!(
+            ApiErrorCode::from("9999"),
+            ApiErrorCode::Unknown("9999".to_string())
+        );
+        assert_eq!(ApiErrorCode::InvalidValue.to_string(), "0503");
+        assert!(ApiErrorCode::SystemError.is_retryable());
+        assert!(!ApiErrorCode::SystemError.is_client_error());
+        assert!(ApiErrorCode::TooManyResults.is_client_error());
+        let unknown = ApiErrorCode::Unknown("9999".to_string());
+        assert!(!unknown.is_retryable() && !unknown.is_client_error());
     }
 
     #[test]
@@ -111,7 +351,9 @@ mod tests {
         </err_item>";
         let e: ApiError = from_str(s).unwrap();
         assert_eq!(e.err_code, "0101");
+        assert_eq!(e.code(), ApiErrorCode::MissingRequired);
         assert_eq!(e.err_fld, "");
+        assert_eq!(e.field(), None);
         assert_eq!(e.err_msg, "検索必須項目が指定されていません。");
     }
 }
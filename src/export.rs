@@ -0,0 +1,585 @@
+//! 事例のCSV, JSON Lines, JSONへの書き出し
+//!
+//! `export` featureが必要
+//!
+//! 事例を1行 (1オブジェクト) に平坦化して書き出す. 列は検索区分ごとに決まっており,
+//! システム管理項目も事例の項目と同じ階層に並べる. キーワードや分類などの複数の値を持つ項目は,
+//! CSVでは [`ExportOptions::separator`] で連結した文字列, JSONでは文字列の配列とする
+//!
+//! 事例を1件ずつ書き出すため, ページングしながら取得した事例もそのまま書き出せる
+//!
+//! # Example
+//!
+//! ```no_run
+//! use crd_api::{client::Client, export::Exporter, request::SearchType};
+//! use futures::StreamExt;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let client = Client::new()?;
+//!     let request = crd_api::builder()
+//!         .search_type(SearchType::Reference)
+//!         .query("question any 読書")
+//!         .build()?;
+//!     let mut exporter = Exporter::csv(std::fs::File::create("references.csv")?);
+//!     let mut items = std::pin::pin!(client.search_all(&request));
+//!     while let Some(item) = items.next().await {
+//!         exporter.write(&item?)?;
+//!     }
+//!     exporter.finish()?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::{self, Write};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    request::SearchType,
+    response::{Bibl, Class, Collection, Manual, Profile, Reference, ResultItem},
+};
+
+/// 書き出し形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// CSV (1行目はヘッダー)
+    #[default]
+    Csv,
+
+    /// 1行に1件のJSON
+    JsonLines,
+
+    /// 整形したJSONの配列
+    Json,
+}
+
+/// 書き出しの設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// CSVで複数の値を連結する区切り文字 (デフォルト: `"; "`)
+    ///
+    /// `keyword`, `class`, `bibl-desc`, `bibl-isbn`, `bibl-note`, `referral`, `contri` に使用する
+    pub separator: String,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            separator: "; ".to_string(),
+        }
+    }
+}
+
+/// 事例を1件ずつ書き出す
+///
+/// 書き出しを終えたら [`finish`](Self::finish) を呼ぶ必要がある
+#[derive(Debug)]
+pub struct Exporter<W: Write> {
+    output: Output<W>,
+    options: ExportOptions,
+    count: usize,
+}
+
+#[derive(Debug)]
+enum Output<W: Write> {
+    Csv {
+        writer: Box<csv::Writer<W>>,
+        search_type: Option<SearchType>,
+    },
+    JsonLines(W),
+    Json(W),
+}
+
+impl<W: Write> Exporter<W> {
+    /// 指定した形式で書き出す
+    pub fn new(writer: W, format: Format) -> Self {
+        let output = match format {
+            Format::Csv => Output::Csv {
+                writer: Box::new(csv::Writer::from_writer(writer)),
+                search_type: None,
+            },
+            Format::JsonLines => Output::JsonLines(writer),
+            Format::Json => Output::Json(writer),
+        };
+        Self {
+            output,
+            options: ExportOptions::default(),
+            count: 0,
+        }
+    }
+
+    /// CSVで書き出す
+    ///
+    /// ヘッダーは最初の事例の検索区分の列となる. 1つのCSVに書き出せるのは1つの検索区分の事例のみ
+    pub fn csv(writer: W) -> Self {
+        Self::new(writer, Format::Csv)
+    }
+
+    /// JSON Linesで書き出す
+    pub fn json_lines(writer: W) -> Self {
+        Self::new(writer, Format::JsonLines)
+    }
+
+    /// 整形したJSONの配列で書き出す
+    pub fn json(writer: W) -> Self {
+        Self::new(writer, Format::Json)
+    }
+
+    /// 設定を変更する
+    pub fn with_options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 書き出した事例の件数
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// 事例を書き出す
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したとき, またはCSVに異なる検索区分の事例を書き出そうとしたときエラーを返す
+    pub fn write(&mut self, item: &ResultItem) -> Result<(), ExportError> {
+        let record = Record::new(item);
+        match &mut self.output {
+            Output::Csv {
+                writer,
+                search_type,
+            } => {
+                let found = item.search_type();
+                match *search_type {
+                    Some(expected) if expected != found => {
+                        return Err(ExportError::MixedTypes { expected, found });
+                    }
+                    Some(_) => {}
+                    None => {
+                        writer.write_record(record.cells.iter().map(|(name, _)| name))?;
+                        *search_type = Some(found);
+                    }
+                }
+                let separator = &self.options.separator;
+                writer.write_record(record.cells.iter().map(|(_, cell)| cell.to_csv(separator)))?;
+            }
+            Output::JsonLines(writer) => {
+                serde_json::to_writer(&mut *writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+            Output::Json(writer) => {
+                writer.write_all(if self.count == 0 { b"[\n" } else { b",\n" })?;
+                let json = serde_json::to_string_pretty(&record)?;
+                for (i, line) in json.lines().enumerate() {
+                    if i > 0 {
+                        writer.write_all(b"\n")?;
+                    }
+                    write!(writer, "  {line}")?;
+                }
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// 複数の事例を書き出す
+    ///
+    /// # Errors
+    ///
+    /// [`write`](Self::write) がエラーを返したときエラーを返す
+    pub fn write_all<'a>(
+        &mut self,
+        items: impl IntoIterator<Item = &'a ResultItem>,
+    ) -> Result<(), ExportError> {
+        items.into_iter().try_for_each(|item| self.write(item))
+    }
+
+    /// 書き出しを終えて出力先を返す
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したときエラーを返す
+    pub fn finish(self) -> Result<W, ExportError> {
+        let mut writer = match self.output {
+            Output::Csv { writer, .. } => writer.into_inner().map_err(|e| e.into_error())?,
+            Output::JsonLines(writer) => writer,
+            Output::Json(mut writer) => {
+                writer.write_all(if self.count == 0 { b"[]\n" } else { b"\n]\n" })?;
+                writer
+            }
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+/// 書き出しのエラー
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    /// CSVに異なる検索区分の事例を書き出そうとした
+    #[error("cannot write {found} to CSV of {expected}")]
+    MixedTypes {
+        /// CSVの検索区分
+        expected: SearchType,
+
+        /// 書き出そうとした事例の検索区分
+        found: SearchType,
+    },
+}
+
+/// 平坦化した項目の値
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cell {
+    Null,
+    Bool(bool),
+    Number(u32),
+    Text(String),
+    List(Vec<String>),
+}
+
+impl Cell {
+    fn to_csv(&self, separator: &str) -> String {
+        match self {
+            Self::Null => String::new(),
+            Self::Bool(b) => b.to_string(),
+            Self::Number(n) => n.to_string(),
+            Self::Text(s) => s.clone(),
+            Self::List(values) => values.join(separator),
+        }
+    }
+}
+
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_none(),
+            Self::Bool(b) => serializer.serialize_bool(*b),
+            Self::Number(n) => serializer.serialize_u32(*n),
+            Self::Text(s) => serializer.serialize_str(s),
+            Self::List(values) => values.serialize(serializer),
+        }
+    }
+}
+
+/// 平坦化した事例
+///
+/// JSONでは先頭に検索区分 (`type`) を加える
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    search_type: SearchType,
+    cells: Vec<(&'static str, Cell)>,
+}
+
+impl Record {
+    fn new(item: &ResultItem) -> Self {
+        let record = Self {
+            search_type: item.search_type(),
+            cells: Vec::new(),
+        };
+        match item {
+            ResultItem::Reference(r) => record.reference(r),
+            ResultItem::Manual(m) => record.manual(m),
+            ResultItem::Collection(c) => record.collection(c),
+            ResultItem::Profile(p) => record.profile(p),
+        }
+    }
+
+    fn reference(self, r: &Reference) -> Self {
+        let s = &r.system;
+        self.text("sys-id", &s.sys_id)
+            .text("reg-id", &r.reg_id)
+            .text("question", &r.question)
+            .text("answer", &r.answer)
+            .date("crt-date", r.crt_date)
+            .flag("solution", r.solution)
+            .list("keyword", &r.keyword)
+            .class(&r.class)
+            .opt("res-type", &r.res_type)
+            .opt("con-type", &r.con_type)
+            .bibl(&r.bibl)
+            .opt("ans-proc", &r.ans_proc)
+            .list("referral", &r.referral)
+            .opt("pre-res", &r.pre_res)
+            .opt("note", &r.note)
+            .opt("ptn-type", &r.ptn_type)
+            .list("contri", &r.contri)
+            .datetime("reg-date", s.reg_date)
+            .datetime("lst-date", s.lst_date)
+            .text("lib-id", &s.lib_id)
+            .text("lib-name", &s.lib_name)
+            .number("file-num", s.file_num)
+            .text("url", &r.url)
+    }
+
+    fn manual(self, m: &Manual) -> Self {
+        let s = &m.system;
+        self.text("sys-id", &s.sys_id)
+            .text("reg-id", &m.reg_id)
+            .text("theme", &m.theme)
+            .text("guide", &m.guide)
+            .date("crt-date", m.crt_date)
+            .flag("completion", m.completion)
+            .list("keyword", &m.keyword)
+            .class(&m.class)
+            .bibl(&m.bibl)
+            .opt("note", &m.note)
+            .datetime("reg-date", s.reg_date)
+            .datetime("lst-date", s.lst_date)
+            .text("lib-id", &s.lib_id)
+            .text("lib-name", &s.lib_name)
+            .number("file-num", s.file_num)
+            .text("url", &m.url)
+    }
+
+    fn collection(self, c: &Collection) -> Self {
+        let s = &c.system;
+        self.text("sys-id", &s.sys_id)
+            .text("reg-id", &c.reg_id)
+            .text("col-name", &c.col_name)
+            .text("pro-key", &c.pro_key)
+            .text("outline", &c.outline)
+            .opt("origin", &c.origin)
+            .opt("restriction", &c.restriction)
+            .opt("catalog", &c.catalog)
+            .opt("literature", &c.literature)
+            .opt("number", &c.number)
+            .flag("continue", c.collection_continue)
+            .list("keyword", &c.keyword)
+            .class(&c.class)
+            .opt("note", &c.note)
+            .datetime("reg-date", s.reg_date)
+            .datetime("lst-date", s.lst_date)
+            .text("lib-id", &s.lib_id)
+            .text("lib-name", &s.lib_name)
+            .number("file-num", s.file_num)
+            .text("url", &c.url)
+    }
+
+    fn profile(self, p: &Profile) -> Self {
+        let s = &p.system;
+        self.text("lib-id", &s.lib_id)
+            .text("lib-type", p.lib_type.code())
+            .text("lib-name", &p.lib_name)
+            .text("abbr", &p.abbr)
+            .text("pro-key", &p.pro_key)
+            .text("zip-code", &p.zip_code)
+            .text("add-pref", &p.add_pref)
+            .text("add-city", &p.add_city)
+            .text("add-street", &p.add_street)
+            .text("tel1", &p.tel1)
+            .opt("tel1-note", &p.tel1_note)
+            .opt("tel2", &p.tel2)
+            .opt("tel2-note", &p.tel2_note)
+            .opt("tel3", &p.tel3)
+            .opt("tel3-note", &p.tel3_note)
+            .opt("fax", &p.fax)
+            .opt("e-mail", &p.e_mail)
+            .opt("lib-url", &p.lib_url)
+            .opt("open-info", &p.open_info)
+            .opt("restriction", &p.restriction)
+            .opt("outline", &p.outline)
+            .opt("feature", &p.feature)
+            .opt("notes", &p.notes)
+            .opt("access", &p.access)
+            .opt("isil", &p.isil)
+            .datetime("reg-date", s.reg_date)
+            .datetime("lst-date", s.lst_date)
+            .number("file-num", s.file_num)
+            .text("url", &p.url)
+    }
+
+    fn push(mut self, name: &'static str, cell: Cell) -> Self {
+        self.cells.push((name, cell));
+        self
+    }
+
+    fn text(self, name: &'static str, value: &str) -> Self {
+        self.push(name, Cell::Text(value.to_string()))
+    }
+
+    fn opt(self, name: &'static str, value: &Option<String>) -> Self {
+        let cell = value.clone().map_or(Cell::Null, Cell::Text);
+        self.push(name, cell)
+    }
+
+    fn flag(self, name: &'static str, value: Option<bool>) -> Self {
+        self.push(name, value.map_or(Cell::Null, Cell::Bool))
+    }
+
+    fn number(self, name: &'static str, value: u32) -> Self {
+        self.push(name, Cell::Number(value))
+    }
+
+    fn date(self, name: &'static str, value: Option<NaiveDate>) -> Self {
+        let cell = value.map_or(Cell::Null, |d| Cell::Text(d.to_string()));
+        self.push(name, cell)
+    }
+
+    fn datetime(self, name: &'static str, value: NaiveDateTime) -> Self {
+        self.push(name, Cell::Text(value.to_string()))
+    }
+
+    fn list(self, name: &'static str, values: &Option<Vec<String>>) -> Self {
+        self.push(name, Cell::List(values.clone().unwrap_or_default()))
+    }
+
+    /// 分類は `NDC9:910.268` のように種類とバージョンを前に付ける
+    fn class(self, classes: &Option<Vec<Class>>) -> Self {
+        let values = classes
+            .iter()
+            .flatten()
+            .map(|c| {
+                let version = c.version.as_deref().unwrap_or_default();
+                format!("{}{version}:{}", c.class_type, c.class)
+            })
+            .collect();
+        self.push("class", Cell::List(values))
+    }
+
+    /// 参考資料は書誌的事項, ISBN, 備考をそれぞれ1列にする
+    ///
+    /// 各列の値は参考資料ごとに1つとし, 項目がない場合は空文字列とするため, 同じ位置の値が同じ参考資料に対応する
+    fn bibl(self, bibls: &Option<Vec<Bibl>>) -> Self {
+        let values = |f: fn(&Bibl) -> &Option<String>| {
+            bibls
+                .iter()
+                .flatten()
+                .map(|b| f(b).clone().unwrap_or_default())
+                .collect()
+        };
+        let desc = values(|b| &b.bibl_desc);
+        let isbn = values(|b| &b.bibl_isbn);
+        let note = values(|b| &b.bibl_note);
+        self.push("bibl-desc", Cell::List(desc))
+            .push("bibl-isbn", Cell::List(isbn))
+            .push("bibl-note", Cell::List(note))
+    }
+}
+
+impl Serialize for Record {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.cells.len() + 1))?;
+        map.serialize_entry("type", &self.search_type)?;
+        for (name, cell) in &self.cells {
+            map.serialize_entry(name, cell)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Corpus;
+
+    fn items(search_type: SearchType) -> Vec<ResultItem> {
+        Corpus::fixtures()
+            .items()
+            .iter()
+            .filter(|item| item.search_type() == search_type)
+            .cloned()
+            .collect()
+    }
+
+    fn export(exporter: Exporter<Vec<u8>>, items: &[ResultItem]) -> String {
+        let mut exporter = exporter;
+        exporter.write_all(items).unwrap();
+        String::from_utf8(exporter.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn csv_test() {
+        let items = items(SearchType::Reference);
+        let options = ExportOptions {
+            separator: "|".to_string(),
+        };
+        let csv = export(Exporter::csv(Vec::new()).with_options(options), &items);
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        assert_eq!(&headers[0], "sys-id");
+        assert_eq!(headers.len(), 25);
+        let records: Vec<_> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), items.len());
+        let column = |name: &str| headers.iter().position(|h| h == name).unwrap();
+        let record = records
+            .iter()
+            .find(|r| &r[column("sys-id")] == "1000000002")
+            .unwrap();
+        assert_eq!(&record[column("class")], "NDC9:910.268|NDC9:760");
+        assert_eq!(&record[column("solution")], "true");
+        assert_eq!(&record[column("lst-date")], "2024-01-05 15:00:00");
+        assert_eq!(&record[column("keyword")], "村上春樹|音楽|小説");
+
+        // ISBNや備考のない参考資料も空の値として位置を揃える
+        let record = records
+            .iter()
+            .find(|r| &r[column("sys-id")] == "1000000001")
+            .unwrap();
+        assert_eq!(
+            &record[column("bibl-desc")],
+            "『読書感想文の書き方』 山田太郎 著 ポプラ社 2015|『作文がすきになる本』 佐藤花子 著 あかね書房 2010"
+        );
+        assert_eq!(&record[column("bibl-isbn")], "978-4-591-14567-0|");
+        assert_eq!(&record[column("bibl-note")], "当館請求記号 019/ヤ|");
+        let record = records
+            .iter()
+            .find(|r| &r[column("sys-id")] == "1000000004")
+            .unwrap();
+        assert_eq!(&record[column("bibl-isbn")], "4873119782|");
+        assert_eq!(&record[column("bibl-note")], "|Web");
+
+        let mut exporter = Exporter::csv(Vec::new());
+        exporter.write(&items[0]).unwrap();
+        let err = exporter.write(&Corpus::fixtures().items()[11]).unwrap_err();
+        assert!(matches!(
+            err,
+            ExportError::MixedTypes {
+                expected: SearchType::Reference,
+                found: SearchType::Profile
+            }
+        ));
+    }
+
+    #[test]
+    fn json_lines_test() {
+        let corpus = Corpus::fixtures();
+        let jsonl = export(Exporter::json_lines(Vec::new()), corpus.items());
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), corpus.items().len());
+        assert_eq!(lines[0]["type"], "reference");
+        assert_eq!(lines[0]["sys-id"], "1000000001");
+        assert!(lines[0]["keyword"].is_array());
+        assert_eq!(lines[2]["crt-date"], serde_json::Value::Null);
+        let profile = lines.last().unwrap();
+        assert_eq!(profile["type"], "profile");
+        assert_eq!(profile["file-num"], 0);
+    }
+
+    #[test]
+    fn json_test() {
+        assert_eq!(export(Exporter::json(Vec::new()), &[]), "[]\n");
+
+        let items = items(SearchType::Manual);
+        let json = export(Exporter::json(Vec::new()), &items);
+        assert!(json.starts_with("[\n  {\n    \"type\": \"manual\",\n"));
+        assert!(json.ends_with("  }\n]\n"));
+        let value: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.len(), items.len());
+        assert_eq!(value[1]["completion"], false);
+    }
+}
@@ -0,0 +1,371 @@
+//! 最終更新日時による差分取得
+//!
+//! 検索区分ごとに取得済みの最終更新日時 (high-water mark) を記録し,
+//! それ以降に更新された事例を取得する. ローカルのミラーを最新に保つために使用する
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//!
+//! use crd_api::{
+//!     client::Client,
+//!     harvest::{HarvestState, Harvester},
+//!     request::SearchType,
+//!     response::ResultItem,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let mut mirror: HashMap<String, ResultItem> = HashMap::new();
+//!     let mut harvester = Harvester::new(Client::new()?, HarvestState::default());
+//!     harvester
+//!         .harvest(SearchType::Reference, |item| {
+//!             mirror.insert(item.id().to_string(), item);
+//!             Ok::<_, std::convert::Infallible>(())
+//!         })
+//!         .await?;
+//!     // 次回は前回の続きから取得するため, 状態を保存しておく
+//!     let state = harvester.into_state();
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    client::{next_position, Client},
+    error::{ApiErrorCode, Error},
+    request::{LibGroup, Request, SearchType, SortKey, SortOrder, MAX_RESULTS_NUM},
+    response::ResultItem,
+};
+
+/// 差分取得の状態
+///
+/// 検索区分ごとに取得済みの事例の最終更新日時の最大値を保持する.
+/// serde で保存・復元できる
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarvestState {
+    marks: HashMap<SearchType, NaiveDateTime>,
+}
+
+impl HarvestState {
+    /// 検索区分の取得済みの最終更新日時
+    pub fn mark(&self, search_type: SearchType) -> Option<NaiveDateTime> {
+        self.marks.get(&search_type).copied()
+    }
+
+    /// 取得済みの最終更新日時を進める
+    ///
+    /// 現在の値より前の日時は無視する
+    pub fn advance(&mut self, search_type: SearchType, lst_date: NaiveDateTime) {
+        let mark = self.marks.entry(search_type).or_insert(lst_date);
+        *mark = (*mark).max(lst_date);
+    }
+
+    /// 検索区分の記録を消去する
+    ///
+    /// 次回は [`HarvestConfig::start`] から取得する
+    pub fn reset(&mut self, search_type: SearchType) {
+        self.marks.remove(&search_type);
+    }
+}
+
+/// 差分取得の設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HarvestConfig {
+    /// 記録がない場合に取得を開始する最終更新日 (デフォルト: 2000-01-01)
+    pub start: NaiveDate,
+
+    /// 1つの期間でページングして取得する最大件数 (デフォルト: 1000)
+    ///
+    /// 期間内のヒット数がこれを超える場合, 期間を2つに分割して取得する.
+    /// 1日の期間で超える場合は分割せずに取得する
+    pub max_hits: u32,
+
+    /// 提供館コード
+    pub lib_id: Option<String>,
+
+    /// 検索対象
+    pub lib_group: Option<LibGroup>,
+}
+
+impl Default for HarvestConfig {
+    fn default() -> Self {
+        Self {
+            start: NaiveDate::from_ymd_opt(2000, 1, 1).unwrap(),
+            max_hits: 1000,
+            lib_id: None,
+            lib_group: None,
+        }
+    }
+}
+
+/// 差分取得の結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarvestSummary {
+    /// 行ったリクエストの回数
+    pub requests: u32,
+
+    /// 取得した期間の数
+    pub windows: u32,
+
+    /// 出力した事例の件数
+    pub upserts: usize,
+
+    /// 取得後の最終更新日時の記録
+    pub mark: Option<NaiveDateTime>,
+}
+
+/// 差分取得のエラー
+#[derive(Error, Debug)]
+pub enum HarvestError<E> {
+    /// 検索に失敗した
+    #[error(transparent)]
+    Search(#[from] Error),
+
+    /// 事例の出力先がエラーを返した
+    #[error("failed to upsert: {0}")]
+    Upsert(E),
+}
+
+/// 最終更新日時による差分取得を行う
+#[derive(Debug, Clone)]
+pub struct Harvester {
+    client: Client,
+    config: HarvestConfig,
+    state: HarvestState,
+}
+
+impl Harvester {
+    /// デフォルト設定で作成する
+    pub fn new(client: Client, state: HarvestState) -> Self {
+        Self::with_config(client, state, HarvestConfig::default())
+    }
+
+    /// 設定を指定して作成する
+    pub fn with_config(client: Client, state: HarvestState, config: HarvestConfig) -> Self {
+        Self {
+            client,
+            config,
+            state,
+        }
+    }
+
+    /// 現在の状態
+    pub fn state(&self) -> &HarvestState {
+        &self.state
+    }
+
+    /// 状態を取り出す
+    pub fn into_state(self) -> HarvestState {
+        self.state
+    }
+
+    /// 前回の記録以降に更新された事例を取得する
+    ///
+    /// 最終更新日の期間を古い順に取得し, 取得した事例を `upsert` に渡す.
+    /// 同じ事例 (システムID, 参加館プロファイルは図書館コード) は1回の取得で1度だけ渡す.
+    /// 記録と同じ日時に更新された事例は再度渡すため, `upsert` は冪等である必要がある
+    ///
+    /// 状態は期間ごとに更新するため, エラーで中断した場合も次回は完了した期間の続きから取得する
+    ///
+    /// # Errors
+    ///
+    /// 検索に失敗したとき, または `upsert` がエラーを返したときエラーを返す
+    pub async fn harvest<F, E>(
+        &mut self,
+        search_type: SearchType,
+        mut upsert: F,
+    ) -> Result<HarvestSummary, HarvestError<E>>
+    where
+        F: FnMut(ResultItem) -> Result<(), E>,
+    {
+        let mark = self.state.mark(search_type);
+        let from = mark.map_or(self.config.start, |m| m.date());
+        let to = Utc::now().date_naive() + Days::new(1);
+        let base = Request {
+            search_type: Some(search_type),
+            lib_id: self.config.lib_id.clone(),
+            lib_group: self.config.lib_group,
+            results_num: Some(MAX_RESULTS_NUM),
+            sort: Some(SortKey::LstDate),
+            sort_order: Some(SortOrder::Asc),
+            ..Default::default()
+        };
+
+        let mut summary = HarvestSummary::default();
+        let mut seen: HashMap<String, NaiveDateTime> = HashMap::new();
+        // 古い期間から取得するため, 新しい期間を先に積む
+        let mut windows = vec![(from, to)];
+        while let Some((from, to)) = windows.pop() {
+            let mut request = base.clone();
+            request.lst_date_from = Some(from);
+            request.lst_date_to = Some(to);
+            request.results_get_position = Some(1);
+            summary.requests += 1;
+            let mut result = match self.client.search(&request).await {
+                Err(e) if is_too_many(&e) && from < to => {
+                    windows.extend(split(from, to));
+                    continue;
+                }
+                res => res?,
+            };
+            if result.hit_num > self.config.max_hits && from < to {
+                windows.extend(split(from, to));
+                continue;
+            }
+
+            summary.windows += 1;
+            let mut window_mark = None;
+            let mut position = 1;
+            loop {
+                let next = next_position(position, &result);
+                for item in result.result {
+                    let lst_date = item.lst_date();
+                    if mark.is_some_and(|m| lst_date < m) {
+                        continue;
+                    }
+                    window_mark = window_mark.max(Some(lst_date));
+                    match seen.get(item.id()) {
+                        Some(seen) if *seen >= lst_date => continue,
+                        _ => {}
+                    }
+                    seen.insert(item.id().to_string(), lst_date);
+                    upsert(item).map_err(HarvestError::Upsert)?;
+                    summary.upserts += 1;
+                }
+                let Some(next) = next else {
+                    break;
+                };
+                position = next;
+                request.results_get_position = Some(position);
+                summary.requests += 1;
+                result = self.client.search(&request).await?;
+            }
+            if let Some(window_mark) = window_mark {
+                self.state.advance(search_type, window_mark);
+            }
+        }
+        summary.mark = self.state.mark(search_type);
+        Ok(summary)
+    }
+}
+
+/// 上限件数を超えたことによるエラーなら [`true`] を返す
+fn is_too_many(error: &Error) -> bool {
+    match error.last_error() {
+        Error::Api(errors) => errors
+            .iter()
+            .any(|e| e.code() == ApiErrorCode::TooManyResults),
+        _ => false,
+    }
+}
+
+/// 期間を2つに分割する
+///
+/// 新しい期間, 古い期間の順に返す
+fn split(from: NaiveDate, to: NaiveDate) -> [(NaiveDate, NaiveDate); 2] {
+    let mid = from + Days::new(((to - from).num_days() / 2) as u64);
+    [(mid + Days::new(1), to), (from, mid)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::mock::{Corpus, MockServer};
+
+    fn datetime(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S").unwrap()
+    }
+
+    #[test]
+    fn split_test() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert_eq!(
+            split(date(1), date(10)),
+            [(date(6), date(10)), (date(1), date(5))]
+        );
+        assert_eq!(
+            split(date(1), date(2)),
+            [(date(2), date(2)), (date(1), date(1))]
+        );
+    }
+
+    #[test]
+    fn state_test() {
+        let mut state = HarvestState::default();
+        assert_eq!(state.mark(SearchType::Reference), None);
+        state.advance(SearchType::Reference, datetime("20240101000000"));
+        state.advance(SearchType::Reference, datetime("20230101000000"));
+        assert_eq!(
+            state.mark(SearchType::Reference),
+            Some(datetime("20240101000000"))
+        );
+        assert_eq!(state.mark(SearchType::Manual), None);
+        state.reset(SearchType::Reference);
+        assert_eq!(state, HarvestState::default());
+    }
+
+    #[tokio::test]
+    async fn harvest_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let config = HarvestConfig {
+            max_hits: 1,
+            ..Default::default()
+        };
+        let mut harvester =
+            Harvester::with_config(server.client().unwrap(), HarvestState::default(), config);
+        let mut ids = Vec::new();
+        let summary = harvester
+            .harvest(SearchType::Reference, |item| {
+                ids.push(item.id().to_string());
+                Ok::<_, Infallible>(())
+            })
+            .await
+            .unwrap();
+        // 最終更新日時の古い順
+        assert_eq!(
+            ids,
+            ["1000000003", "1000000001", "1000000002", "1000000004"]
+        );
+        assert_eq!(summary.upserts, 4);
+        // ヒット数が2件以上の期間は分割される
+        assert!(summary.requests > summary.windows);
+        assert_eq!(summary.mark, Some(datetime("20240315110000")));
+
+        // 2回目は記録以降に更新された事例のみ
+        let mut ids = Vec::new();
+        let summary = harvester
+            .harvest(SearchType::Reference, |item| {
+                ids.push(item.id().to_string());
+                Ok::<_, Infallible>(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(ids, ["1000000004"]);
+        assert_eq!(summary.mark, Some(datetime("20240315110000")));
+
+        let state = harvester.into_state();
+        assert_eq!(state.mark(SearchType::Manual), None);
+    }
+
+    #[tokio::test]
+    async fn harvest_upsert_error_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let mut harvester = Harvester::new(server.client().unwrap(), HarvestState::default());
+        let err = harvester
+            .harvest(SearchType::Manual, |_| Err("disk full"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HarvestError::Upsert("disk full")));
+        assert_eq!(harvester.state().mark(SearchType::Manual), None);
+    }
+}
@@ -0,0 +1,573 @@
+//! 取得した事例のローカルな全文検索インデックス
+//!
+//! `index` featureが必要
+//!
+//! [tantivy](https://docs.rs/tantivy) のインデックスに事例を登録し, [`cql::Query`] をAPIと同じ意味で評価する.
+//! [`cql::Index`] の項目ごとにフィールドを作成し, 部分一致の項目は1文字ずつのn-gramとして,
+//! 完全一致・前方一致の項目はそのままの文字列として登録する.
+//! 部分一致の検索語は連続する文字のフレーズ検索となるため, 日本語の文も分かち書きせずに検索できる
+//!
+//! # Example
+//!
+//! ```no_run
+//! use crd_api::{
+//!     client::Client,
+//!     cql::Query,
+//!     harvest::{HarvestState, Harvester},
+//!     index::LocalIndex,
+//!     request::SearchType,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let mut index = LocalIndex::open_or_create("crd-index")?;
+//!     let mut harvester = Harvester::new(Client::new()?, HarvestState::default());
+//!     harvester
+//!         .harvest(SearchType::Reference, |item| index.upsert(&item))
+//!         .await?;
+//!     index.commit()?;
+//!
+//!     let query: Query = "question any 読書 and solution = resolved".parse()?;
+//!     for item in index.search(SearchType::Reference, &query)? {
+//!         println!("{}", item.title());
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::{collections::BTreeSet, fs, path::Path, str::CharIndices};
+
+use tantivy::{
+    collector::DocSetCollector,
+    directory::{error::OpenDirectoryError, MmapDirectory},
+    query::{AllQuery, BooleanQuery, EmptyQuery, Occur, PhraseQuery, Query, RegexQuery, TermQuery},
+    schema::{
+        Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING,
+    },
+    tokenizer::{LowerCaser, TextAnalyzer, Token, TokenStream, Tokenizer},
+    Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, TantivyError, Term,
+};
+use thiserror::Error;
+
+use crate::{
+    cql::{
+        self, Boolean, CollectionIndex, ManualIndex, ProfileIndex, ReferenceIndex, Relation,
+        ValidationError,
+    },
+    request::SearchType,
+    response::{
+        fields::{self, Match},
+        ResultItem,
+    },
+};
+
+/// 部分一致の項目に使用するトークナイザーの名前
+const TOKENIZER: &str = "crd_char";
+
+/// インデックスの書き込みに使用するメモリ
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// 事例の全文検索インデックス
+pub struct LocalIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: IndexWriter,
+    fields: Fields,
+}
+
+impl LocalIndex {
+    /// ディレクトリのインデックスを開く
+    ///
+    /// ディレクトリやインデックスが存在しない場合は作成する
+    ///
+    /// # Errors
+    ///
+    /// インデックスを開けないときエラーを返す
+    pub fn open_or_create(path: impl AsRef<Path>) -> Result<Self, IndexError> {
+        fs::create_dir_all(&path).map_err(TantivyError::from)?;
+        let dir = MmapDirectory::open(path)?;
+        Self::new(Index::open_or_create(dir, schema())?)
+    }
+
+    /// メモリ上にインデックスを作成する
+    ///
+    /// # Errors
+    ///
+    /// インデックスの作成に失敗したときエラーを返す
+    pub fn create_in_ram() -> Result<Self, IndexError> {
+        Self::new(Index::create_in_ram(schema()))
+    }
+
+    fn new(index: Index) -> Result<Self, IndexError> {
+        index.tokenizers().register(
+            TOKENIZER,
+            TextAnalyzer::builder(CharTokenizer::default())
+                .filter(LowerCaser)
+                .build(),
+        );
+        let fields = Fields::new(&index.schema())?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = index.writer(WRITER_MEMORY)?;
+        Ok(Self {
+            index,
+            reader,
+            writer,
+            fields,
+        })
+    }
+
+    /// 事例を追加する
+    ///
+    /// 同じ事例 (システムID, 参加館プロファイルは図書館コード) が登録済みの場合は置き換える.
+    /// [`commit`](Self::commit) するまで検索結果には反映されない
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したときエラーを返す
+    pub fn upsert(&mut self, item: &ResultItem) -> Result<(), IndexError> {
+        let search_type = item.search_type();
+        let key = key(search_type, item.id());
+        self.writer
+            .delete_term(Term::from_field_text(self.fields.key, &key));
+
+        let mut doc = TantivyDocument::new();
+        doc.add_text(self.fields.search_type, search_type.as_str());
+        doc.add_text(self.fields.key, &key);
+        doc.add_text(self.fields.xml, item.to_xml());
+        for index in index_names(search_type) {
+            let Some(field) = fields::field(item, index) else {
+                continue;
+            };
+            let target = self.fields.get(index, field.matching)?;
+            for value in field.values {
+                doc.add_text(target, value);
+            }
+        }
+        self.writer.add_document(doc)?;
+        Ok(())
+    }
+
+    /// 事例を削除する
+    ///
+    /// [`commit`](Self::commit) するまで検索結果には反映されない
+    pub fn remove(&mut self, search_type: SearchType, id: &str) {
+        self.writer.delete_term(Term::from_field_text(
+            self.fields.key,
+            &key(search_type, id),
+        ));
+    }
+
+    /// 追加・削除を確定して検索結果に反映する
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したときエラーを返す
+    pub fn commit(&mut self) -> Result<(), IndexError> {
+        self.writer.commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// 登録されている事例の件数
+    pub fn num_items(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
+    /// 検索条件に一致する事例を最終更新日時の新しい順に返す
+    ///
+    /// [`SearchType::All`] の場合は全ての検索区分から `anywhere` で検索する
+    ///
+    /// # Errors
+    ///
+    /// 検索区分で使用できない項目がクエリーに含まれているとき, または読み込みに失敗したときエラーを返す
+    pub fn search(
+        &self,
+        search_type: SearchType,
+        query: &cql::Query,
+    ) -> Result<Vec<ResultItem>, IndexError> {
+        query.validate_for(search_type)?;
+        let mut clauses = vec![(Occur::Must, self.query(query)?)];
+        if search_type != SearchType::All {
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(self.fields.search_type, search_type.as_str()),
+                    IndexRecordOption::Basic,
+                )),
+            ));
+        }
+
+        let searcher = self.reader.searcher();
+        let addresses = searcher.search(&BooleanQuery::new(clauses), &DocSetCollector)?;
+        let mut items = addresses
+            .into_iter()
+            .map(|address| {
+                let doc: TantivyDocument = searcher.doc(address)?;
+                let xml = doc
+                    .get_first(self.fields.xml)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                Ok(ResultItem::from_xml(xml)?)
+            })
+            .collect::<Result<Vec<_>, IndexError>>()?;
+        items.sort_by(|a, b| {
+            b.lst_date()
+                .cmp(&a.lst_date())
+                .then_with(|| a.id().cmp(b.id()))
+        });
+        Ok(items)
+    }
+
+    /// 検索条件を tantivy のクエリーに変換する
+    fn query(&self, query: &cql::Query) -> Result<Box<dyn Query>, TantivyError> {
+        match query {
+            cql::Query::SearchClause {
+                index,
+                relation,
+                search_term,
+            } => {
+                let index = index.as_str();
+                let occur = match relation {
+                    Relation::All if search_term.is_empty() => return Ok(Box::new(AllQuery)),
+                    Relation::All => Occur::Must,
+                    Relation::Any => Occur::Should,
+                    Relation::Equal => {
+                        let terms: Vec<&str> = search_term.iter().map(cql::Term::as_str).collect();
+                        return self.term_query(index, &terms.join(" "));
+                    }
+                };
+                let clauses = search_term
+                    .iter()
+                    .map(|term| Ok((occur, self.term_query(index, term.as_str())?)))
+                    .collect::<Result<_, TantivyError>>()?;
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+            cql::Query::ScopedClause {
+                left,
+                boolean,
+                right,
+            } => {
+                let (left, right) = (self.query(left)?, self.query(right)?);
+                let clauses = match boolean {
+                    Boolean::And => vec![(Occur::Must, left), (Occur::Must, right)],
+                    Boolean::Or => vec![(Occur::Should, left), (Occur::Should, right)],
+                    Boolean::Not => vec![(Occur::Must, left), (Occur::MustNot, right)],
+                };
+                Ok(Box::new(BooleanQuery::new(clauses)))
+            }
+        }
+    }
+
+    /// 1つの検索語に一致する事例のクエリー
+    ///
+    /// 項目の一致方法は検索区分によらず同じため, 値が登録されているフィールドのみ一致する
+    fn term_query(&self, index: &str, term: &str) -> Result<Box<dyn Query>, TantivyError> {
+        let partial = self.fields.get(index, Match::Partial)?;
+        let exact = self.fields.get(index, Match::Exact)?;
+        let prefix = self.fields.get(index, Match::Prefix)?;
+
+        let mut terms = Vec::new();
+        let mut analyzer = self.index.tokenizer_for_field(partial)?;
+        analyzer.token_stream(term).process(&mut |token| {
+            terms.push((token.position, Term::from_field_text(partial, &token.text)));
+        });
+        let partial: Box<dyn Query> = match terms.len() {
+            0 => Box::new(EmptyQuery),
+            1 => Box::new(TermQuery::new(
+                terms.remove(0).1,
+                IndexRecordOption::WithFreqs,
+            )),
+            _ => Box::new(PhraseQuery::new_with_offset(terms)),
+        };
+        let exact = TermQuery::new(Term::from_field_text(exact, term), IndexRecordOption::Basic);
+        let prefix = RegexQuery::from_pattern(&(escape(term) + ".*"), prefix)?;
+        Ok(Box::new(BooleanQuery::new(vec![
+            (Occur::Should, partial),
+            (Occur::Should, Box::new(exact)),
+            (Occur::Should, Box::new(prefix)),
+        ])))
+    }
+}
+
+impl std::fmt::Debug for LocalIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalIndex")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+/// インデックスの読み書きのエラー
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error(transparent)]
+    Tantivy(#[from] TantivyError),
+
+    #[error(transparent)]
+    OpenDirectory(#[from] OpenDirectoryError),
+
+    /// 検索区分で使用できない項目がクエリーに含まれている
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+
+    /// 登録されている事例の解析に失敗した
+    #[error(transparent)]
+    De(#[from] quick_xml::DeError),
+}
+
+/// インデックスのフィールド
+#[derive(Debug, Clone)]
+struct Fields {
+    schema: Schema,
+    search_type: Field,
+    key: Field,
+    xml: Field,
+}
+
+impl Fields {
+    fn new(schema: &Schema) -> Result<Self, TantivyError> {
+        Ok(Self {
+            schema: schema.clone(),
+            search_type: schema.get_field("type")?,
+            key: schema.get_field("key")?,
+            xml: schema.get_field("xml")?,
+        })
+    }
+
+    /// クエリー対象項目の一致方法ごとのフィールド
+    fn get(&self, index: &str, matching: Match) -> Result<Field, TantivyError> {
+        self.schema.get_field(&field_name(index, matching))
+    }
+}
+
+/// インデックスのスキーマ
+///
+/// 検索区分と事例のキー, 事例のxmlに加えて, 全ての検索区分のクエリー対象項目について
+/// 一致方法ごとのフィールドを持つ
+fn schema() -> Schema {
+    let mut builder = Schema::builder();
+    builder.add_text_field("type", STRING);
+    builder.add_text_field("key", STRING);
+    builder.add_text_field("xml", STORED);
+    let text = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    let names: BTreeSet<_> = [
+        SearchType::Reference,
+        SearchType::Manual,
+        SearchType::Collection,
+        SearchType::Profile,
+    ]
+    .into_iter()
+    .flat_map(index_names)
+    .collect();
+    for name in names {
+        builder.add_text_field(&field_name(name, Match::Partial), text.clone());
+        builder.add_text_field(&field_name(name, Match::Exact), STRING);
+        builder.add_text_field(&field_name(name, Match::Prefix), STRING);
+    }
+    builder.build()
+}
+
+fn field_name(index: &str, matching: Match) -> String {
+    match matching {
+        Match::Partial => index.to_string(),
+        Match::Exact => format!("{index}_exact"),
+        Match::Prefix => format!("{index}_prefix"),
+    }
+}
+
+/// 検索区分で使用できるクエリー対象項目
+fn index_names(search_type: SearchType) -> Vec<&'static str> {
+    match search_type {
+        SearchType::Reference => ReferenceIndex::VALUES.iter().map(|i| i.as_str()).collect(),
+        SearchType::Manual => ManualIndex::VALUES.iter().map(|i| i.as_str()).collect(),
+        SearchType::Collection => CollectionIndex::VALUES.iter().map(|i| i.as_str()).collect(),
+        SearchType::Profile => ProfileIndex::VALUES.iter().map(|i| i.as_str()).collect(),
+        SearchType::All => vec!["anywhere"],
+    }
+}
+
+fn key(search_type: SearchType, id: &str) -> String {
+    format!("{search_type}/{id}")
+}
+
+/// 正規表現の特殊文字をエスケープする
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if r"\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 1文字ずつ区切るトークナイザー
+#[derive(Debug, Clone, Default)]
+struct CharTokenizer {
+    token: Token,
+}
+
+struct CharTokenStream<'a> {
+    chars: CharIndices<'a>,
+    token: &'a mut Token,
+}
+
+impl Tokenizer for CharTokenizer {
+    type TokenStream<'a> = CharTokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        self.token.reset();
+        CharTokenStream {
+            chars: text.char_indices(),
+            token: &mut self.token,
+        }
+    }
+}
+
+impl TokenStream for CharTokenStream<'_> {
+    fn advance(&mut self) -> bool {
+        let Some((offset, c)) = self.chars.next() else {
+            return false;
+        };
+        self.token.text.clear();
+        self.token.text.push(c);
+        self.token.offset_from = offset;
+        self.token.offset_to = offset + c.len_utf8();
+        self.token.position = self.token.position.wrapping_add(1);
+        true
+    }
+
+    fn token(&self) -> &Token {
+        self.token
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Corpus;
+
+    fn index() -> LocalIndex {
+        let mut index = LocalIndex::create_in_ram().unwrap();
+        for item in Corpus::fixtures().items() {
+            index.upsert(item).unwrap();
+        }
+        index.commit().unwrap();
+        index
+    }
+
+    #[test]
+    fn tokenizer_test() {
+        let mut tokenizer = CharTokenizer::default();
+        let mut tokens = Vec::new();
+        tokenizer
+            .token_stream("村上 R")
+            .process(&mut |t| tokens.push((t.position, t.text.clone())));
+        assert_eq!(
+            tokens,
+            [
+                (0, "村".to_string()),
+                (1, "上".to_string()),
+                (2, " ".to_string()),
+                (3, "R".to_string())
+            ]
+        );
+        assert_eq!(escape("a.b-c"), r"a\.b\-c");
+    }
+
+    #[test]
+    fn search_test() {
+        let index = index();
+        assert_eq!(index.num_items(), 12);
+        let corpus = Corpus::fixtures();
+        for (search_type, query) in [
+            (
+                SearchType::Reference,
+                "question any 本 音楽 and solution = resolved",
+            ),
+            (
+                SearchType::Reference,
+                "question any 本 and answer any 村上春樹",
+            ),
+            (
+                SearchType::Reference,
+                "ndc = 91 or keyword all 江戸時代 地図 not solution = 0",
+            ),
+            (SearchType::All, "anywhere = 地図"),
+            (SearchType::Profile, "lib-type = 23 or address = 大阪"),
+            (
+                SearchType::Reference,
+                "reg-id = NDL or reg-id = 2021 or sys-id = 1000000002",
+            ),
+        ] {
+            let request = crate::builder()
+                .search_type(search_type)
+                .query(query)
+                .build()
+                .unwrap();
+            let mut expected = corpus.search(&request).unwrap().result;
+            expected.sort_by(|a, b| {
+                b.lst_date()
+                    .cmp(&a.lst_date())
+                    .then_with(|| a.id().cmp(b.id()))
+            });
+            assert!(!expected.is_empty(), "{query}");
+            let query: cql::Query = query.parse().unwrap();
+            assert_eq!(
+                index.search(search_type, &query).unwrap(),
+                expected,
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn upsert_test() {
+        let mut index = index();
+        let query = cql::Query::any("question", &["読書"]);
+        let hits = index.search(SearchType::Reference, &query).unwrap();
+        let [ResultItem::Reference(reference)] = hits.as_slice() else {
+            panic!("unexpected hits: {hits:?}");
+        };
+
+        let mut updated = reference.clone();
+        updated.question = "おすすめの絵本を知りたい".to_string();
+        index.upsert(&ResultItem::Reference(updated)).unwrap();
+        index.commit().unwrap();
+        assert_eq!(index.num_items(), 12);
+        assert!(index
+            .search(SearchType::Reference, &query)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            index
+                .search(
+                    SearchType::Reference,
+                    &cql::Query::any("question", &["絵本"])
+                )
+                .unwrap()
+                .len(),
+            1
+        );
+
+        index.remove(SearchType::Reference, &reference.system.sys_id);
+        index.commit().unwrap();
+        assert_eq!(index.num_items(), 11);
+
+        let err = index.search(SearchType::Manual, &query).unwrap_err();
+        assert!(matches!(err, IndexError::Validation(_)));
+    }
+}
@@ -6,15 +6,16 @@
 //!
 //! # Examples
 //!
-//! ```
+//! ```no_run
 //! use crd_api::cql::Query;
+//! use crd_api::request::SearchType;
 //! use crd_api::response::Reference;
 //!
 //! #[tokio::main]
 //! async fn main() -> anyhow::Result<()> {
 //!     // 質問に「読書」を含むレファレンス事例を検索
 //!     let request = crd_api::builder()
-//!         .search_type("reference")
+//!         .search_type(SearchType::Reference)
 //!         .query(Query::any("question", &["読書"]).to_string())
 //!         .build()?;
 //!     let result = request.search().await?;
@@ -25,11 +26,25 @@
 //! ```
 //!
 
+#[macro_use]
+mod macros;
+
+pub mod citation;
 pub mod client;
 pub mod cql;
 pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod harvest;
+#[cfg(feature = "index")]
+pub mod index;
+pub mod linked_data;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod request;
 pub mod response;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 pub fn builder() -> request::RequestBuilder {
     request::RequestBuilder::default()
@@ -41,12 +56,13 @@ mod tests {
 
     #[tokio::test]
     async fn search_query_test() {
-        builder()
+        let server = mock::MockServer::start(mock::Corpus::fixtures())
+            .await
+            .unwrap();
+        let request = builder()
             .query(cql::Query::new(&["some"]).to_string())
             .build()
-            .unwrap()
-            .search()
-            .await
             .unwrap();
+        server.client().unwrap().search(&request).await.unwrap();
     }
 }
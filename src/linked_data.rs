@@ -0,0 +1,525 @@
+//! Dublin Core (oai_dc) とschema.orgのJSON-LDへの変換
+//!
+//! 事例をLinked Dataとして公開するためのメタデータに変換する.
+//! 分類 (NDC) は `NDC9:910.268` のように種類とバージョンを前に付けた主題とする
+//!
+//! JSON-LDは [`Serialize`] を実装した [`Node`] として返すため, 任意のJSONシリアライザーで出力できる
+//!
+//! # Example
+//!
+//! ```
+//! use crd_api::{linked_data, response::ResultItem};
+//!
+//! # fn run(item: &ResultItem) -> anyhow::Result<()> {
+//! // oai_dc形式のXML
+//! let xml = linked_data::dublin_core(item).to_xml();
+//! // schema.orgのJSON-LD
+//! let json = serde_json::to_string_pretty(&linked_data::json_ld(item))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::Serialize;
+
+use crate::response::{Bibl, Class, Collection, Manual, Profile, Reference, ResultItem};
+
+const OAI_DC: &str = "http://www.openarchives.org/OAI/2.0/oai_dc/";
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const XSI: &str = "http://www.w3.org/2001/XMLSchema-instance";
+const SCHEMA_LOCATION: &str =
+    "http://www.openarchives.org/OAI/2.0/oai_dc/ http://www.openarchives.org/OAI/2.0/oai_dc.xsd";
+const SCHEMA_ORG: &str = "https://schema.org";
+
+/// Dublin Core (oai_dc) のメタデータ
+///
+/// 参照: <https://www.openarchives.org/OAI/openarchivesprotocol.html#dublincore>
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename = "oai_dc:dc")]
+pub struct DublinCore {
+    #[serde(rename = "@xmlns:oai_dc")]
+    xmlns_oai_dc: &'static str,
+
+    #[serde(rename = "@xmlns:dc")]
+    xmlns_dc: &'static str,
+
+    #[serde(rename = "@xmlns:xsi")]
+    xmlns_xsi: &'static str,
+
+    #[serde(rename = "@xsi:schemaLocation")]
+    schema_location: &'static str,
+
+    /// タイトル
+    #[serde(rename = "dc:title")]
+    pub title: Vec<String>,
+
+    /// 作成者
+    #[serde(rename = "dc:creator")]
+    pub creator: Vec<String>,
+
+    /// 主題 (キーワード, 分類)
+    #[serde(rename = "dc:subject")]
+    pub subject: Vec<String>,
+
+    /// 内容記述
+    #[serde(rename = "dc:description")]
+    pub description: Vec<String>,
+
+    /// 寄与者
+    #[serde(rename = "dc:contributor")]
+    pub contributor: Vec<String>,
+
+    /// 日付 (YYYY-MM-DD)
+    #[serde(rename = "dc:date")]
+    pub date: Vec<String>,
+
+    /// 資源タイプ
+    #[serde(rename = "dc:type")]
+    pub dc_type: Vec<String>,
+
+    /// 識別子 (URL)
+    #[serde(rename = "dc:identifier")]
+    pub identifier: Vec<String>,
+
+    /// 情報源 (参考資料)
+    #[serde(rename = "dc:source")]
+    pub source: Vec<String>,
+
+    /// 言語
+    #[serde(rename = "dc:language")]
+    pub language: Vec<String>,
+
+    /// 空間的範囲
+    #[serde(rename = "dc:coverage")]
+    pub coverage: Vec<String>,
+
+    /// 権利 (利用条件)
+    #[serde(rename = "dc:rights")]
+    pub rights: Vec<String>,
+}
+
+impl Default for DublinCore {
+    fn default() -> Self {
+        Self {
+            xmlns_oai_dc: OAI_DC,
+            xmlns_dc: DC,
+            xmlns_xsi: XSI,
+            schema_location: SCHEMA_LOCATION,
+            title: Vec::new(),
+            creator: Vec::new(),
+            subject: Vec::new(),
+            description: Vec::new(),
+            contributor: Vec::new(),
+            date: Vec::new(),
+            dc_type: Vec::new(),
+            identifier: Vec::new(),
+            source: Vec::new(),
+            language: Vec::new(),
+            coverage: Vec::new(),
+            rights: Vec::new(),
+        }
+    }
+}
+
+impl DublinCore {
+    /// `oai_dc:dc` 要素のXMLに変換する
+    pub fn to_xml(&self) -> String {
+        quick_xml::se::to_string(self).unwrap()
+    }
+}
+
+/// 事例をDublin Coreに変換する
+pub fn dublin_core(item: &ResultItem) -> DublinCore {
+    let mut dc = DublinCore {
+        identifier: vec![item.url().to_string()],
+        language: vec!["jpn".to_string()],
+        ..Default::default()
+    };
+    match item {
+        ResultItem::Reference(r) => {
+            dc.title.push(r.question.clone());
+            dc.creator.push(r.system.lib_name.clone());
+            dc.subject = subjects(&r.keyword, &r.class);
+            dc.description.push(r.answer.clone());
+            dc.description.extend(r.ans_proc.clone());
+            dc.contributor.extend(r.contri.iter().flatten().cloned());
+            dc.date.extend(r.crt_date.map(|d| d.to_string()));
+            dc.dc_type = vec!["Text".to_string(), "レファレンス事例".to_string()];
+            dc.source = sources(&r.bibl);
+        }
+        ResultItem::Manual(m) => {
+            dc.title.push(m.theme.clone());
+            dc.creator.push(m.system.lib_name.clone());
+            dc.subject = subjects(&m.keyword, &m.class);
+            dc.description.push(m.guide.clone());
+            dc.date.extend(m.crt_date.map(|d| d.to_string()));
+            dc.dc_type = vec!["Text".to_string(), "調べ方マニュアル".to_string()];
+            dc.source = sources(&m.bibl);
+        }
+        ResultItem::Collection(c) => {
+            dc.title.push(c.col_name.clone());
+            dc.creator.push(c.system.lib_name.clone());
+            dc.subject = subjects(&c.keyword, &c.class);
+            dc.description.push(c.outline.clone());
+            dc.description.extend(c.origin.clone());
+            dc.dc_type = vec!["Collection".to_string(), "特別コレクション".to_string()];
+            dc.rights.extend(c.restriction.clone());
+        }
+        ResultItem::Profile(p) => {
+            dc.title.push(p.lib_name.clone());
+            dc.description.extend(p.outline.clone());
+            dc.description.extend(p.feature.clone());
+            dc.dc_type.push("参加館プロファイル".to_string());
+            dc.identifier.extend(p.lib_url.clone());
+            dc.identifier.extend(p.isil.clone());
+            dc.coverage.push(p.add_pref.clone());
+            dc.rights.extend(p.restriction.clone());
+        }
+    }
+    dc
+}
+
+/// schema.orgのノード
+///
+/// 値がない項目は出力しない
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    /// `@context` (最上位のノードのみ)
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<&'static str>,
+
+    /// `@type`
+    #[serde(rename = "@type")]
+    pub node_type: &'static str,
+
+    /// `@id`
+    #[serde(rename = "@id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alternate_name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub about: Vec<Node>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub term_code: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_defined_term_set: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accepted_answer: Option<Box<Node>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_answer: Option<Box<Node>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub citation: Vec<Node>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub isbn: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contributor: Vec<Node>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Box<Node>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Box<Node>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postal_code: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_region: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_locality: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub street_address: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub telephone: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fax_number: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub same_as: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_created: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_modified: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Node {
+    fn new(node_type: &'static str) -> Self {
+        Self {
+            node_type,
+            ..Default::default()
+        }
+    }
+}
+
+/// 事例をschema.orgのJSON-LDに変換する
+///
+/// - レファレンス事例: `Question` (解決なら `acceptedAnswer`, それ以外は `suggestedAnswer`)
+/// - 調べ方マニュアル: `HowTo`
+/// - 特別コレクション: `Collection`
+/// - 参加館プロファイル: `Library`
+pub fn json_ld(item: &ResultItem) -> Node {
+    let mut node = match item {
+        ResultItem::Reference(r) => reference(r),
+        ResultItem::Manual(m) => manual(m),
+        ResultItem::Collection(c) => collection(c),
+        ResultItem::Profile(p) => profile(p),
+    };
+    node.context = Some(SCHEMA_ORG);
+    node.id = Some(item.url().to_string());
+    node.url = Some(item.url().to_string());
+    node.identifier = Some(item.id().to_string());
+    node.date_modified = Some(item.lst_date().format("%Y-%m-%dT%H:%M:%S").to_string());
+    node
+}
+
+fn reference(r: &Reference) -> Node {
+    let answer = Node {
+        text: Some(r.answer.clone()),
+        ..Node::new("Answer")
+    };
+    let (accepted_answer, suggested_answer) = match r.solution {
+        Some(true) => (Some(answer.into()), None),
+        _ => (None, Some(answer.into())),
+    };
+    Node {
+        name: Some(r.question.clone()),
+        text: Some(r.question.clone()),
+        keywords: r.keyword.clone().unwrap_or_default(),
+        about: about(&r.class),
+        accepted_answer,
+        suggested_answer,
+        citation: citations(&r.bibl),
+        contributor: r
+            .contri
+            .iter()
+            .flatten()
+            .map(|name| Node {
+                name: Some(name.clone()),
+                ..Node::new("Organization")
+            })
+            .collect(),
+        provider: Some(provider(&r.system.lib_id, &r.system.lib_name)),
+        date_created: r.crt_date.map(|d| d.to_string()),
+        ..Node::new("Question")
+    }
+}
+
+fn manual(m: &Manual) -> Node {
+    Node {
+        name: Some(m.theme.clone()),
+        text: Some(m.guide.clone()),
+        keywords: m.keyword.clone().unwrap_or_default(),
+        about: about(&m.class),
+        citation: citations(&m.bibl),
+        provider: Some(provider(&m.system.lib_id, &m.system.lib_name)),
+        date_created: m.crt_date.map(|d| d.to_string()),
+        ..Node::new("HowTo")
+    }
+}
+
+fn collection(c: &Collection) -> Node {
+    Node {
+        name: Some(c.col_name.clone()),
+        alternate_name: Some(c.pro_key.clone()),
+        description: Some(c.outline.clone()),
+        keywords: c.keyword.clone().unwrap_or_default(),
+        about: about(&c.class),
+        provider: Some(provider(&c.system.lib_id, &c.system.lib_name)),
+        ..Node::new("Collection")
+    }
+}
+
+fn profile(p: &Profile) -> Node {
+    let address = Node {
+        postal_code: Some(p.zip_code.clone()),
+        address_region: Some(p.add_pref.clone()),
+        address_locality: Some(p.add_city.clone()),
+        street_address: Some(p.add_street.clone()),
+        ..Node::new("PostalAddress")
+    };
+    Node {
+        name: Some(p.lib_name.clone()),
+        alternate_name: Some(p.abbr.clone()).filter(|abbr| *abbr != p.lib_name),
+        description: p.feature.clone().or_else(|| p.outline.clone()),
+        address: Some(address.into()),
+        telephone: [Some(&p.tel1), p.tel2.as_ref(), p.tel3.as_ref()]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        fax_number: p.fax.clone(),
+        email: p.e_mail.clone(),
+        same_as: p.lib_url.clone(),
+        ..Node::new("Library")
+    }
+}
+
+/// 提供館
+fn provider(lib_id: &str, lib_name: &str) -> Box<Node> {
+    Node {
+        identifier: Some(lib_id.to_string()),
+        name: Some(lib_name.to_string()),
+        ..Node::new("Library")
+    }
+    .into()
+}
+
+/// 分類を `DefinedTerm` とする
+fn about(classes: &Option<Vec<Class>>) -> Vec<Node> {
+    classes
+        .iter()
+        .flatten()
+        .map(|c| Node {
+            term_code: Some(c.class.clone()),
+            in_defined_term_set: Some(class_scheme(c)),
+            ..Node::new("DefinedTerm")
+        })
+        .collect()
+}
+
+/// 参考資料を `CreativeWork` とする
+fn citations(bibls: &Option<Vec<Bibl>>) -> Vec<Node> {
+    bibls
+        .iter()
+        .flatten()
+        .filter(|b| b.bibl_desc.is_some() || b.bibl_isbn.is_some())
+        .map(|b| Node {
+            name: b.bibl_desc.clone(),
+            isbn: b.bibl_isbn.clone(),
+            ..Node::new("CreativeWork")
+        })
+        .collect()
+}
+
+/// 分類の種類とバージョン (例: `NDC9`)
+fn class_scheme(class: &Class) -> String {
+    format!(
+        "{}{}",
+        class.class_type,
+        class.version.as_deref().unwrap_or_default()
+    )
+}
+
+fn subjects(keywords: &Option<Vec<String>>, classes: &Option<Vec<Class>>) -> Vec<String> {
+    keywords
+        .iter()
+        .flatten()
+        .cloned()
+        .chain(
+            classes
+                .iter()
+                .flatten()
+                .map(|c| format!("{}:{}", class_scheme(c), c.class)),
+        )
+        .collect()
+}
+
+fn sources(bibls: &Option<Vec<Bibl>>) -> Vec<String> {
+    bibls
+        .iter()
+        .flatten()
+        .filter_map(|b| b.bibl_desc.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Corpus;
+
+    #[test]
+    fn dublin_core_test() {
+        let corpus = Corpus::fixtures();
+        let xml = dublin_core(&corpus.items()[1]).to_xml();
+        assert!(xml.starts_with(r#"<oai_dc:dc xmlns:oai_dc="http://www.openarchives.org/OAI/2.0/oai_dc/" xmlns:dc="http://purl.org/dc/elements/1.1/""#));
+        assert!(xml.contains("<dc:creator>"));
+        assert!(xml.contains("<dc:subject>村上春樹</dc:subject>"));
+        assert!(xml.contains("<dc:subject>NDC9:910.268</dc:subject>"));
+        assert!(xml.contains("<dc:date>2022-08-05</dc:date>"));
+        assert!(xml.contains(
+            "<dc:identifier>https://crd.ndl.go.jp/reference/detail?page=ref_view&amp;id=1000000002</dc:identifier>"
+        ));
+        assert!(xml.ends_with("<dc:language>jpn</dc:language></oai_dc:dc>"));
+        // 値のない項目は出力しない
+        assert!(!xml.contains("dc:rights"));
+
+        let dc = dublin_core(&corpus.items()[8]);
+        assert_eq!(dc.title, ["国立国会図書館"]);
+        assert_eq!(
+            dc.identifier,
+            [
+                "https://crd.ndl.go.jp/reference/detail?page=pro_view&id=1110001",
+                "https://www.ndl.go.jp/",
+                "JP-1000001"
+            ]
+        );
+    }
+
+    #[test]
+    fn json_ld_test() {
+        let corpus = Corpus::fixtures();
+        let json = serde_json::to_value(json_ld(&corpus.items()[1])).unwrap();
+        assert_eq!(json["@context"], "https://schema.org");
+        assert_eq!(json["@type"], "Question");
+        assert_eq!(json["identifier"], "1000000002");
+        assert_eq!(json["acceptedAnswer"]["@type"], "Answer");
+        assert!(json.get("suggestedAnswer").is_none());
+        assert_eq!(json["about"][0]["termCode"], "910.268");
+        assert_eq!(json["about"][0]["inDefinedTermSet"], "NDC9");
+        assert_eq!(json["citation"][0]["isbn"], "9784537257830");
+        assert_eq!(json["provider"]["@type"], "Library");
+        assert_eq!(json["dateCreated"], "2022-08-05");
+        assert_eq!(json["dateModified"], "2024-01-05T15:00:00");
+
+        // 未解決の事例
+        let json = serde_json::to_value(json_ld(&corpus.items()[2])).unwrap();
+        assert!(json.get("acceptedAnswer").is_none());
+        assert_eq!(json["suggestedAnswer"]["@type"], "Answer");
+
+        let json = serde_json::to_value(json_ld(&corpus.items()[8])).unwrap();
+        assert_eq!(json["@type"], "Library");
+        assert_eq!(json["address"]["addressRegion"], "東京都");
+        assert_eq!(json["telephone"][0], "03-3581-2331");
+        assert!(json.get("alternateName").is_none());
+
+        let json = serde_json::to_value(json_ld(&corpus.items()[6])).unwrap();
+        assert_eq!(json["@type"], "Collection");
+        assert!(json["about"][0]["@context"].is_null());
+    }
+}
@@ -0,0 +1,56 @@
+/// 値が文字列で表されるパラメータのenumを定義する
+///
+/// [`Display`](std::fmt::Display), [`FromStr`](std::str::FromStr) と serde の実装を生成する
+macro_rules! param_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident => $value:literal,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                #[serde(rename = $value)]
+                $variant,
+            )*
+        }
+
+        impl $name {
+            /// 全ての値
+            pub const VALUES: &'static [Self] = &[$(Self::$variant),*];
+
+            /// パラメータの値
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $value,)*
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = $crate::request::InvalidParamError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($value => Ok(Self::$variant),)*
+                    _ => Err($crate::request::InvalidParamError {
+                        param: stringify!($name),
+                        value: s.to_string(),
+                    }),
+                }
+            }
+        }
+    };
+}
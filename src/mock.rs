@@ -0,0 +1,437 @@
+//! オフラインでのテストや開発に使用するモックサーバー
+//!
+//! `mock` featureが必要
+//!
+//! [`Corpus`] に登録した事例に対して検索リクエストを評価し, APIと同じ形式のXMLを返す.
+//! 検索語の一致判定や並び順は実際のAPIを簡略化したもので, 適合度は考慮しない
+//!
+//! # Example
+//!
+//! ```
+//! use crd_api::{
+//!     mock::{Corpus, MockServer},
+//!     request::{Request, SearchType},
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let server = MockServer::start(Corpus::fixtures()).await?;
+//!     let client = server.client()?;
+//!     let request = crd_api::builder()
+//!         .search_type(SearchType::Reference)
+//!         .query("question any 読書")
+//!         .build()?;
+//!     let result = client.search(&request).await?;
+//!     assert_eq!(result.hit_num, 1);
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+
+use crate::{
+    cql::{self, Boolean, Query},
+    error::{ApiError, ApiErrorCode, ApiErrors},
+    request::{LibGroup, Request, SearchType, SortKey, SortOrder, MAX_RESULTS_NUM},
+    response::{fields, ResultItem, ResultSet},
+};
+
+mod server;
+
+pub use server::MockServer;
+
+const FIXTURES: &str = include_str!("mock/fixtures.xml");
+
+/// モックサーバーが検索対象とする事例の集合
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Corpus {
+    items: Vec<ResultItem>,
+}
+
+impl Corpus {
+    /// 指定した事例から作成する
+    pub fn new(items: Vec<ResultItem>) -> Self {
+        Self { items }
+    }
+
+    /// 組み込みのサンプルデータ
+    ///
+    /// レファレンス事例, 調べ方マニュアル, 特別コレクション, 参加館プロファイルを数件ずつ含む
+    pub fn fixtures() -> Self {
+        Self::from_xml(FIXTURES).unwrap()
+    }
+
+    /// APIの返却結果と同じ形式のxmlから作成する
+    ///
+    /// # Errors
+    ///
+    /// xmlの解析に失敗したときエラーを返す
+    pub fn from_xml(s: &str) -> Result<Self, quick_xml::DeError> {
+        Ok(Self::new(ResultSet::from_xml(s)?.result))
+    }
+
+    /// 登録されている事例
+    pub fn items(&self) -> &[ResultItem] {
+        &self.items
+    }
+
+    /// 事例を追加する
+    pub fn push(&mut self, item: ResultItem) {
+        self.items.push(item);
+    }
+
+    /// リクエストを評価して検索結果を返す
+    ///
+    /// # Errors
+    ///
+    /// リクエストが不正なとき, APIと同じエラーコードのエラーを返す
+    pub fn search(&self, request: &Request) -> Result<ResultSet, ApiErrors> {
+        let mut errors = Vec::new();
+        let has_date = [
+            request.crt_date_from,
+            request.crt_date_to,
+            request.reg_date_from,
+            request.reg_date_to,
+            request.lst_date_from,
+            request.lst_date_to,
+        ]
+        .iter()
+        .any(Option::is_some);
+        if request.query.is_none() && !has_date {
+            errors.push(api_error(ApiErrorCode::MissingRequired, ""));
+        }
+        let search_type = request.search_type.unwrap_or_default();
+        let query = match request.query.as_deref().map(cql::parse).transpose() {
+            Ok(query) => query,
+            Err(_) => {
+                errors.push(api_error(ApiErrorCode::InvalidQuery, "query"));
+                None
+            }
+        };
+        if let Some(Err(e)) = query.as_ref().map(|q| q.validate_for(search_type)) {
+            errors.push(api_error(ApiErrorCode::InvalidValue, e.index.as_str()));
+        }
+        let position = request.results_get_position.unwrap_or(1);
+        if position < 1 {
+            errors.push(api_error(ApiErrorCode::OutOfRange, "results_get_position"));
+        }
+        let num = request.results_num.unwrap_or(MAX_RESULTS_NUM);
+        if !(0..=MAX_RESULTS_NUM).contains(&num) {
+            errors.push(api_error(ApiErrorCode::OutOfRange, "results_num"));
+        }
+        let sort = request.sort.unwrap_or_default();
+        if !sort.is_valid_for(search_type) {
+            errors.push(api_error(ApiErrorCode::InvalidValue, "sort"));
+        }
+        if !errors.is_empty() {
+            return Err(ApiErrors::new(errors));
+        }
+
+        let mut hits: Vec<&ResultItem> = self
+            .items
+            .iter()
+            .filter(|item| search_type == SearchType::All || item.search_type() == search_type)
+            .filter(|item| self.filter(item, request))
+            .filter(|item| query.as_ref().is_none_or(|q| matches(item, q)))
+            .collect();
+        if sort != SortKey::Fit {
+            hits.sort_by(|a, b| {
+                let ord = compare(a, b, sort);
+                match request.sort_order.unwrap_or_default() {
+                    SortOrder::Asc => ord,
+                    SortOrder::Desc => ord.reverse(),
+                }
+            });
+        }
+        let result = hits
+            .iter()
+            .skip(position as usize - 1)
+            .take(num as usize)
+            .map(|item| (*item).clone())
+            .collect();
+        Ok(ResultSet::new(hits.len() as u32, position as u32, result))
+    }
+
+    /// 検索条件以外のパラメータによる絞り込み
+    fn filter(&self, item: &ResultItem, request: &Request) -> bool {
+        fn within(date: Option<NaiveDate>, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+            if from.is_none() && to.is_none() {
+                return true;
+            }
+            date.is_some_and(|d| from.is_none_or(|f| f <= d) && to.is_none_or(|t| d <= t))
+        }
+
+        if request
+            .lib_id
+            .as_deref()
+            .is_some_and(|id| id != item.lib_id())
+        {
+            return false;
+        }
+        if let Some(group) = request.lib_group.filter(|g| *g != LibGroup::All) {
+            if self.lib_group(item.lib_id()) != Some(group) {
+                return false;
+            }
+        }
+        within(crt_date(item), request.crt_date_from, request.crt_date_to)
+            && within(
+                Some(item.reg_date().date()),
+                request.reg_date_from,
+                request.reg_date_to,
+            )
+            && within(
+                Some(item.lst_date().date()),
+                request.lst_date_from,
+                request.lst_date_to,
+            )
+    }
+
+    /// 参加館プロファイルの館種から図書館の種別を求める
+    fn lib_group(&self, lib_id: &str) -> Option<LibGroup> {
+        let profile = self.items.iter().find_map(|item| match item {
+            ResultItem::Profile(p) if p.system.lib_id == lib_id => Some(p),
+            _ => None,
+        })?;
+        profile.lib_type.lib_group()
+    }
+}
+
+/// APIと同じ形式のエラー情報を作成する
+fn api_error(code: ApiErrorCode, field: &str) -> ApiError {
+    let err_msg = match code {
+        ApiErrorCode::MissingRequired => "検索必須項目が指定されていません。".to_string(),
+        ApiErrorCode::NotNumeric => format!("【{field}】には数値を指定してください。"),
+        ApiErrorCode::InvalidDate => format!("【{field}】の日付の形式が正しくありません。"),
+        ApiErrorCode::OutOfRange => {
+            format!("【{field}】に指定できる範囲外の値が指定されています。")
+        }
+        ApiErrorCode::InvalidQuery => "検索条件の書式が正しくありません。".to_string(),
+        _ => format!("【{field}】に使用できない値が指定されています。"),
+    };
+    ApiError {
+        err_code: code.to_string(),
+        err_fld: field.to_string(),
+        err_msg,
+    }
+}
+
+/// 事例が検索条件に一致するなら [`true`] を返す
+fn matches(item: &ResultItem, query: &Query) -> bool {
+    match query {
+        Query::SearchClause {
+            index,
+            relation,
+            search_term,
+        } => fields::field(item, index.as_str()).is_some_and(|f| f.is_match(relation, search_term)),
+        Query::ScopedClause {
+            left,
+            boolean,
+            right,
+        } => match boolean {
+            Boolean::And => matches(item, left) && matches(item, right),
+            Boolean::Or => matches(item, left) || matches(item, right),
+            Boolean::Not => matches(item, left) && !matches(item, right),
+        },
+    }
+}
+
+fn crt_date(item: &ResultItem) -> Option<NaiveDate> {
+    match item {
+        ResultItem::Reference(r) => r.crt_date,
+        ResultItem::Manual(m) => m.crt_date,
+        _ => None,
+    }
+}
+
+fn reg_id(item: &ResultItem) -> &str {
+    match item {
+        ResultItem::Reference(r) => &r.reg_id,
+        ResultItem::Manual(m) => &m.reg_id,
+        ResultItem::Collection(c) => &c.reg_id,
+        ResultItem::Profile(_) => "",
+    }
+}
+
+fn pro_key(item: &ResultItem) -> &str {
+    match item {
+        ResultItem::Collection(c) => &c.pro_key,
+        ResultItem::Profile(p) => &p.pro_key,
+        _ => "",
+    }
+}
+
+/// ソート項目で比較する. 第2ソートキーは最終更新日時, 第3ソートキーは管理番号
+///
+/// アクセス数と拍手数は保持していないため比較しない
+fn compare(a: &ResultItem, b: &ResultItem, sort: SortKey) -> Ordering {
+    match sort {
+        SortKey::RegId => reg_id(a).cmp(reg_id(b)),
+        SortKey::CrtDate => crt_date(a).cmp(&crt_date(b)),
+        SortKey::RegDate => a.reg_date().cmp(&b.reg_date()),
+        SortKey::ProKey => pro_key(a).cmp(pro_key(b)),
+        SortKey::LstDate | SortKey::Fit | SortKey::AccessNum | SortKey::ApplauseNum => {
+            Ordering::Equal
+        }
+    }
+    .then_with(|| a.lst_date().cmp(&b.lst_date()))
+    .then_with(|| reg_id(a).cmp(reg_id(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder;
+
+    fn search(request: Request) -> ResultSet {
+        Corpus::fixtures().search(&request).unwrap()
+    }
+
+    fn ids(result: &ResultSet) -> Vec<&str> {
+        result.iter().map(ResultItem::id).collect()
+    }
+
+    #[test]
+    fn fixtures_test() {
+        let corpus = Corpus::fixtures();
+        assert_eq!(corpus.items().len(), 12);
+        let xml = ResultSet::new(12, 1, corpus.items().to_vec()).to_xml();
+        assert_eq!(Corpus::from_xml(&xml).unwrap(), corpus);
+    }
+
+    #[test]
+    fn query_test() {
+        let result = search(
+            builder()
+                .search_type(SearchType::Reference)
+                .query("question any 本 音楽 and solution = resolved")
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(result.hit_num, 2);
+        assert_eq!(ids(&result), ["1000000001", "1000000002"]);
+
+        let result = search(
+            builder()
+                .search_type(SearchType::Reference)
+                .query("question any 本 and answer any 村上春樹")
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["1000000002"]);
+
+        let result = search(
+            builder()
+                .search_type(SearchType::Reference)
+                .query("ndc = 91 or keyword all 江戸時代 地図 not solution = 0")
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["1000000003"]);
+
+        let result = search(Request::new("地図"));
+        assert_eq!(ids(&result), ["1000000003", "2000000002", "3000000001"]);
+
+        let result = search(
+            builder()
+                .search_type(SearchType::Profile)
+                .query("lib-type = 23 or address = 大阪")
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["2210001", "3310001"]);
+
+        let result = search(
+            builder()
+                .search_type(SearchType::Profile)
+                .query(r#"lib-type = "大学図書館(私立大学)""#)
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["3310001"]);
+    }
+
+    #[test]
+    fn filter_test() {
+        let result = search(
+            builder()
+                .crt_date_from(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["1000000001", "1000000004", "2000000001"]);
+
+        let result = search(
+            builder()
+                .query("anywhere = 文学")
+                .lib_group(LibGroup::Academic)
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["1000000002", "3000000002"]);
+
+        let result = search(
+            builder()
+                .query("anywhere = 地図")
+                .lib_id("2210001")
+                .lst_date_to(NaiveDate::from_ymd_opt(2022, 12, 31).unwrap())
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(ids(&result), ["1000000003", "2000000002"]);
+    }
+
+    #[test]
+    fn sort_and_page_test() {
+        let request = builder()
+            .search_type(SearchType::Reference)
+            .lst_date_from(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap())
+            .sort(SortKey::CrtDate)
+            .sort_order(SortOrder::Asc)
+            .results_get_position(2)
+            .results_num(2)
+            .build()
+            .unwrap();
+        let result = search(request);
+        assert_eq!(result.hit_num, 4);
+        assert_eq!(result.results_get_position, 2);
+        assert_eq!(result.results_num, 2);
+        assert_eq!(ids(&result), ["1000000002", "1000000001"]);
+    }
+
+    #[test]
+    fn error_test() {
+        let codes = |request: Request| -> Vec<(ApiErrorCode, String)> {
+            Corpus::fixtures()
+                .search(&request)
+                .unwrap_err()
+                .iter()
+                .map(|e| (e.code(), e.err_fld.clone()))
+                .collect()
+        };
+        assert_eq!(
+            codes(Request::default()),
+            [(ApiErrorCode::MissingRequired, String::new())]
+        );
+        assert_eq!(
+            codes(builder().query("question any").build().unwrap()),
+            [(ApiErrorCode::InvalidQuery, "query".to_string())]
+        );
+        assert_eq!(
+            codes(
+                builder()
+                    .search_type(SearchType::Manual)
+                    .query("question any 本")
+                    .results_num(201)
+                    .build()
+                    .unwrap()
+            ),
+            [
+                (ApiErrorCode::InvalidValue, "question".to_string()),
+                (ApiErrorCode::OutOfRange, "results_num".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+use reqwest::StatusCode;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task::JoinHandle,
+};
+
+use super::{api_error, Corpus};
+use crate::{
+    client::Client,
+    error::{ApiErrorCode, ApiErrors},
+    request::{LibGroup, Request, SearchType, SortKey, SortOrder},
+};
+
+/// APIのパス
+const PATH: &str = "/api/refsearch";
+
+/// [`Corpus`] を検索対象とするローカルのHTTPサーバー
+///
+/// `/api/refsearch` へのGETリクエストを受け付け, APIと同じ形式のXMLを返す.
+/// 受け取ったリクエストの記録や, 障害を再現するためのエラー応答と遅延の設定ができる.
+/// サーバーは [`MockServer`] がドロップされると停止する
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+/// リクエストの間で共有するサーバーの状態
+#[derive(Debug, Default)]
+struct State {
+    corpus: Corpus,
+    requests: Mutex<Vec<String>>,
+    failures: Mutex<Option<(StatusCode, usize)>>,
+    latency: Mutex<Duration>,
+}
+
+impl MockServer {
+    /// ローカルホストの空いているポートでサーバーを起動する
+    ///
+    /// # Errors
+    ///
+    /// ソケットのバインドに失敗したときエラーを返す
+    pub async fn start(corpus: Corpus) -> io::Result<Self> {
+        Self::bind("127.0.0.1:0", corpus).await
+    }
+
+    /// 指定したアドレスでサーバーを起動する
+    ///
+    /// # Errors
+    ///
+    /// ソケットのバインドに失敗したときエラーを返す
+    pub async fn bind(addr: impl ToSocketAddrs, corpus: Corpus) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State {
+            corpus,
+            ..Default::default()
+        });
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle(stream, state.clone()));
+                }
+            }
+        });
+        Ok(Self { addr, state, task })
+    }
+
+    /// サーバーのアドレス
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// APIのエンドポイントに相当するURL
+    pub fn url(&self) -> String {
+        format!("http://{}{PATH}", self.addr)
+    }
+
+    /// サーバーにリクエストを送る [`Client`] を作成する
+    ///
+    /// # Errors
+    ///
+    /// [`Client`] の作成に失敗したときエラーを返す
+    pub fn client(&self) -> Result<Client, crate::error::Error> {
+        Client::builder().base_url(self.url()).build()
+    }
+
+    /// これまでに受け取ったリクエストのパスとクエリ
+    pub fn requests(&self) -> Vec<String> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// 次の `count` 件のリクエストに, 検索せずに `status` のステータスを返す
+    pub fn fail_next(&self, status: StatusCode, count: usize) {
+        *self.state.failures.lock().unwrap() = Some((status, count));
+    }
+
+    /// 各リクエストに応答するまでの待機時間 (デフォルト: 待機しない)
+    pub fn set_latency(&self, latency: Duration) {
+        *self.state.latency.lock().unwrap() = latency;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: Arc<State>) {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    let head = String::from_utf8_lossy(&buf);
+    let target = head.split_whitespace().nth(1).unwrap_or_default();
+    state.requests.lock().unwrap().push(target.to_string());
+    let failure = {
+        let mut failures = state.failures.lock().unwrap();
+        match failures.as_mut() {
+            Some((status, count)) if *count > 0 => {
+                *count -= 1;
+                Some(*status)
+            }
+            _ => None,
+        }
+    };
+    let latency = *state.latency.lock().unwrap();
+    if !latency.is_zero() {
+        tokio::time::sleep(latency).await;
+    }
+    let (status, body) = match failure {
+        Some(status) => (
+            status,
+            status.canonical_reason().unwrap_or_default().to_string(),
+        ),
+        None => respond(&state.corpus, target),
+    };
+    let resp = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/xml; charset=UTF-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(resp.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// リクエストのパスとクエリに対するステータスコードとレスポンスボディ
+fn respond(corpus: &Corpus, target: &str) -> (StatusCode, String) {
+    let (path, qs) = target.split_once('?').unwrap_or((target, ""));
+    if path.trim_end_matches('/') != PATH {
+        return (StatusCode::NOT_FOUND, String::new());
+    }
+    let result = parse_request(qs).and_then(|request| corpus.search(&request));
+    match result {
+        Ok(result_set) => (StatusCode::OK, result_set.to_xml()),
+        Err(errors) => (StatusCode::OK, errors.to_xml()),
+    }
+}
+
+/// クエリストリングを [`Request`] に変換する
+///
+/// 値の形式が正しくないパラメータは, APIと同じエラーコードのエラーとする
+fn parse_request(qs: &str) -> Result<Request, ApiErrors> {
+    let params: BTreeMap<String, String> = serde_qs::from_str(qs)
+        .map_err(|_| ApiErrors::new(vec![api_error(ApiErrorCode::InvalidValue, "")]))?;
+    let errors: Vec<_> = params
+        .iter()
+        .filter_map(|(key, value)| {
+            let valid = match key.as_str() {
+                "crt-date_from" | "crt-date_to" | "reg-date_from" | "reg-date_to"
+                | "lst-date_from" | "lst-date_to" => {
+                    return NaiveDate::parse_from_str(value, "%Y%m%d")
+                        .is_err()
+                        .then(|| api_error(ApiErrorCode::InvalidDate, key));
+                }
+                "results_get_position" | "results_num" => {
+                    return value
+                        .parse::<i32>()
+                        .is_err()
+                        .then(|| api_error(ApiErrorCode::NotNumeric, key));
+                }
+                "type" => value.parse::<SearchType>().is_ok(),
+                "lib-group" => value.parse::<LibGroup>().is_ok(),
+                "sort" => value.parse::<SortKey>().is_ok(),
+                "sort_order" => value.parse::<SortOrder>().is_ok(),
+                _ => true,
+            };
+            (!valid).then(|| api_error(ApiErrorCode::InvalidValue, key))
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(ApiErrors::new(errors));
+    }
+    serde_qs::from_str(qs)
+        .map_err(|_| ApiErrors::new(vec![api_error(ApiErrorCode::InvalidValue, "")]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::Error, response::ResultSet};
+
+    #[test]
+    fn respond_test() {
+        let corpus = Corpus::fixtures();
+        let request = crate::builder()
+            .search_type(SearchType::Reference)
+            .query("question any 本 音楽 and solution = 0")
+            .crt_date_from(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap())
+            .build()
+            .unwrap();
+        let (status, body) = respond(&corpus, &format!("{PATH}?{}", request.query_string()));
+        assert_eq!(status, StatusCode::OK);
+        let result = ResultSet::from_xml(&body).unwrap();
+        assert_eq!(result, corpus.search(&request).unwrap());
+        assert_eq!(result.hit_num, 2);
+
+        assert_eq!(respond(&corpus, "/foo?query=rust").0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn respond_error_test() {
+        let corpus = Corpus::fixtures();
+        let (status, body) = respond(
+            &corpus,
+            "/api/refsearch?type=foo&crt-date_from=2000-01-01&results_num=ten",
+        );
+        assert_eq!(status, StatusCode::OK);
+        let errors = ApiErrors::from_xml(&body).unwrap();
+        let fields: Vec<_> = errors.iter().map(|e| (e.code(), e.field())).collect();
+        assert_eq!(
+            fields,
+            [
+                (ApiErrorCode::InvalidDate, Some("crt-date_from")),
+                (ApiErrorCode::NotNumeric, Some("results_num")),
+                (ApiErrorCode::InvalidValue, Some("type")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn server_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        let client = server.client().unwrap();
+        let result = client.search(&Request::new("地図")).await.unwrap();
+        assert_eq!(result.hit_num, 3);
+
+        let err = client
+            .search(&crate::builder().query("question any 本").build().unwrap())
+            .await
+            .unwrap_err();
+        let Error::Api(errors) = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            errors.iter().next().unwrap().code(),
+            ApiErrorCode::InvalidValue
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_next_test() {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        server.fail_next(StatusCode::SERVICE_UNAVAILABLE, 1);
+        server.set_latency(Duration::from_millis(10));
+        let client = server.client().unwrap();
+        let request = Request::new("地図");
+        let err = client.search(&request).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Status(StatusCode::SERVICE_UNAVAILABLE)
+        ));
+        assert_eq!(client.search(&request).await.unwrap().hit_num, 3);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0], format!("{PATH}?{}", request.query_string()));
+    }
+}
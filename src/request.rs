@@ -1,8 +1,20 @@
 use chrono::NaiveDate;
 use derive_builder::Builder;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
 
-use crate::{client::Client, error::Error, response::ResultSet};
+use crate::{
+    client::Client,
+    cql::{Query, Relation, Term},
+    error::Error,
+    response::ResultSet,
+};
+
+/// APIのエンドポイント
+pub const DEFAULT_ENDPOINT: &str = "https://crd.ndl.go.jp/api/refsearch";
+
+/// 1回のリクエストで取得できる検索結果の最大件数
+pub const MAX_RESULTS_NUM: i32 = 200;
 
 /// リクエストパラメータ
 ///
@@ -13,10 +25,12 @@ use crate::{client::Client, error::Error, response::ResultSet};
 /// ```
 /// use anyhow::Result;
 ///
+/// use crd_api::request::SearchType;
+///
 /// fn main() -> Result<()> {
 ///     // 質問に rust を含むリファレンス事例を検索するリクエストを作成
 ///     let request = crd_api::builder()
-///         .search_type("reference")
+///         .search_type(SearchType::Reference)
 ///         .query("question = rust")
 ///         .build()?;
 ///     let url = request.url();
@@ -27,19 +41,14 @@ use crate::{client::Client, error::Error, response::ResultSet};
 /// }
 /// ```
 #[derive(Serialize, Deserialize, Debug, Clone, Default, Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
 pub struct Request {
-    /// 検索区分
+    /// 検索区分 (デフォルト: [`SearchType::All`])
     ///
-    ///    - `reference`: レファレンス事例
-    ///    - `manual`: 調べ方マニュアル
-    ///    - `collection`: 特別コレクション
-    ///    - `profile`: 参加館プロファイル
-    ///    - `all`: すべてを対象 (デフォルト)
-    ///
-    /// `all` の場合, [`query`](Self::query) は `anywhere` のみ使用可能
+    /// [`SearchType::All`] の場合, [`query`](Self::query) は `anywhere` のみ使用可能
     #[serde(rename = "type")]
     #[builder(default, setter(strip_option, into))]
-    pub search_type: Option<String>,
+    pub search_type: Option<SearchType>,
 
     /// 検索条件 (いずれか必須)
     ///
@@ -49,32 +58,62 @@ pub struct Request {
     pub query: Option<String>,
 
     /// 事例作成日 FROM (いずれか必須)
-    #[serde(rename = "crt-date_from", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "crt-date_from",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub crt_date_from: Option<NaiveDate>,
 
     /// 事例作成日 TO (いずれか必須)
-    #[serde(rename = "crt-date_to", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "crt-date_to",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub crt_date_to: Option<NaiveDate>,
 
     /// 登録日 FROM (いずれか必須)
-    #[serde(rename = "reg-date_from", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "reg-date_from",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub reg_date_from: Option<NaiveDate>,
 
     /// 登録日 TO (いずれか必須)
-    #[serde(rename = "reg-date_to", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "reg-date_to",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub reg_date_to: Option<NaiveDate>,
 
     /// 最終更新日 FROM (いずれか必須)
-    #[serde(rename = "lst-date_from", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "lst-date_from",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub lst_date_from: Option<NaiveDate>,
 
     /// 最終更新日 TO (いずれか必須)
-    #[serde(rename = "lst-date_to", serialize_with = "ser_date_opt")]
+    #[serde(
+        rename = "lst-date_to",
+        serialize_with = "ser_date_opt",
+        deserialize_with = "de_date_opt",
+        default
+    )]
     #[builder(default, setter(strip_option, into))]
     pub lst_date_to: Option<NaiveDate>,
 
@@ -86,27 +125,130 @@ pub struct Request {
     #[builder(default, setter(strip_option, into))]
     pub lib_id: Option<String>,
 
-    /// 検索対象
-    ///
-    /// - `all`: 全館 (デフォルト)
-    /// - `ndl`: 国立国会図書館
-    /// - `public`: 公共図書館
-    /// - `academic`: 大学図書館
-    /// - `special`: 専門図書館
-    /// - `school`: 学校図書館
-    /// - `archives`: アーカイブズ
+    /// 検索対象 (デフォルト: [`LibGroup::All`])
     #[serde(rename = "lib-group")]
     #[builder(default, setter(strip_option, into))]
-    pub lib_group: Option<String>,
+    pub lib_group: Option<LibGroup>,
 
     /// 検索結果取得位置 (デフォルト: 1)
     #[builder(default, setter(strip_option, into))]
     pub results_get_position: Option<i32>,
 
-    /// 検索結果返却件数 (デフォルト: 200)
+    /// 検索結果返却件数 (デフォルト: 200, 最大: [`MAX_RESULTS_NUM`])
     #[builder(default, setter(strip_option, into))]
     pub results_num: Option<i32>,
 
+    /// ソート項目 (デフォルト: [`SortKey::Fit`])
+    ///
+    /// 検索区分によって使用できる項目が異なる ([`SortKey::is_valid_for`] 参照).
+    /// すべて第2ソートキーは最終更新日, 第3ソートキーは登録番号となる
+    #[builder(default, setter(strip_option, into))]
+    pub sort: Option<SortKey>,
+
+    /// ソート条件 (デフォルト: [`SortOrder::Desc`])
+    #[builder(default, setter(strip_option, into))]
+    pub sort_order: Option<SortOrder>,
+}
+
+impl RequestBuilder {
+    fn validate(&self) -> Result<(), String> {
+        let search_type = self.search_type.flatten().unwrap_or_default();
+        if let Some(Some(sort)) = self.sort {
+            if !sort.is_valid_for(search_type) {
+                return Err(format!(
+                    "sort key `{sort}` is not available for search type `{search_type}`"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// パラメータの値が不正なときのエラー
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid value `{value}` for {param}")]
+pub struct InvalidParamError {
+    /// パラメータの型名
+    pub param: &'static str,
+
+    /// 不正な値
+    pub value: String,
+}
+
+param_enum! {
+    /// 検索区分
+    #[derive(Default)]
+    pub enum SearchType {
+        /// レファレンス事例
+        Reference => "reference",
+
+        /// 調べ方マニュアル
+        Manual => "manual",
+
+        /// 特別コレクション
+        Collection => "collection",
+
+        /// 参加館プロファイル
+        Profile => "profile",
+
+        /// すべてを対象 (デフォルト)
+        #[default]
+        All => "all",
+    }
+}
+
+impl SearchType {
+    /// 検索区分で使用できるソート項目
+    ///
+    /// [`SearchType::All`] の場合は全ての項目
+    pub fn sort_keys(&self) -> &'static [SortKey] {
+        use SortKey::*;
+        match self {
+            Self::Reference | Self::Manual => &[
+                Fit,
+                RegId,
+                CrtDate,
+                RegDate,
+                LstDate,
+                AccessNum,
+                ApplauseNum,
+            ],
+            Self::Collection => &[Fit, RegId, RegDate, LstDate, AccessNum, ApplauseNum],
+            Self::Profile => &[Fit, ProKey, RegDate, LstDate, AccessNum, ApplauseNum],
+            Self::All => SortKey::VALUES,
+        }
+    }
+}
+
+param_enum! {
+    /// 検索対象
+    #[derive(Default)]
+    pub enum LibGroup {
+        /// 全館 (デフォルト)
+        #[default]
+        All => "all",
+
+        /// 国立国会図書館
+        Ndl => "ndl",
+
+        /// 公共図書館
+        Public => "public",
+
+        /// 大学図書館
+        Academic => "academic",
+
+        /// 専門図書館
+        Special => "special",
+
+        /// 学校図書館
+        School => "school",
+
+        /// アーカイブズ
+        Archives => "archives",
+    }
+}
+
+param_enum! {
     /// ソート項目
     ///
     /// - レファレンス事例
@@ -139,17 +281,53 @@ pub struct Request {
     ///     - `lst-date`: 最終更新日時
     ///     - `access-num`: アクセス数
     ///     - `applause-num`: 拍手数
-    ///
-    /// すべて第2ソートキーは最終更新日, 第3ソートキーは登録番号となる
-    #[builder(default, setter(strip_option, into))]
-    pub sort: Option<String>,
+    #[derive(Default)]
+    pub enum SortKey {
+        /// 適合度 (デフォルト)
+        #[default]
+        Fit => "fit",
+
+        /// 管理番号
+        RegId => "reg-id",
+
+        /// 事例作成日, 調べ方作成日
+        CrtDate => "crt-date",
+
+        /// 登録日時
+        RegDate => "reg-date",
 
+        /// 最終更新日時
+        LstDate => "lst-date",
+
+        /// アクセス数
+        AccessNum => "access-num",
+
+        /// 拍手数
+        ApplauseNum => "applause-num",
+
+        /// 図書館ヨミ
+        ProKey => "pro-key",
+    }
+}
+
+impl SortKey {
+    /// 指定した検索区分で使用できるなら [`true`] を返す
+    pub fn is_valid_for(&self, search_type: SearchType) -> bool {
+        search_type.sort_keys().contains(self)
+    }
+}
+
+param_enum! {
     /// ソート条件
-    ///
-    /// - `asc`: 昇順
-    /// - `desc`: 降順 (デフォルト)
-    #[builder(default, setter(strip_option, into))]
-    pub sort_order: Option<String>,
+    #[derive(Default)]
+    pub enum SortOrder {
+        /// 昇順
+        Asc => "asc",
+
+        /// 降順 (デフォルト)
+        #[default]
+        Desc => "desc",
+    }
 }
 
 fn ser_date_opt<S>(date: &Option<NaiveDate>, serializer: S) -> Result<S::Ok, S::Error>
@@ -163,11 +341,28 @@ where
     }
 }
 
+fn de_date_opt<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .filter(|s| !s.is_empty())
+        .map(|s| NaiveDate::parse_from_str(&s, "%Y%m%d").map_err(serde::de::Error::custom))
+        .transpose()
+}
+
 impl Request {
     /// 簡易検索のリクエストを作成する
+    ///
+    /// 検索語は `"` で囲み, 必要に応じてエスケープする
     pub fn new(search_term: &str) -> Self {
+        let query = Query::SearchClause {
+            index: "anywhere".into(),
+            relation: Relation::Equal,
+            search_term: vec![Term::phrase(search_term)],
+        };
         Self {
-            query: Some(format!(r#"anywhere = "{search_term}""#)),
+            query: Some(query.to_string()),
             ..Default::default()
         }
     }
@@ -179,9 +374,16 @@ impl Request {
 
     /// リクエストURL
     pub fn url(&self) -> String {
-        const ENDPOINT: &'static str = "https://crd.ndl.go.jp/api/refsearch";
+        self.url_with_base(DEFAULT_ENDPOINT)
+    }
+
+    /// 指定したエンドポイントに対するリクエストURL
+    ///
+    /// `base` の末尾の `?` や `/` は無視される
+    pub fn url_with_base(&self, base: &str) -> String {
+        let base = base.trim_end_matches(['?', '/']);
         let qs = self.query_string();
-        format!("{ENDPOINT}?{qs}")
+        format!("{base}?{qs}")
     }
 
     /// リクエストを行って検索結果を取得する
@@ -196,64 +398,154 @@ impl Request {
     pub async fn search(&self) -> Result<ResultSet, Error> {
         Client::new()?.search(self).await
     }
+
+    /// リクエストを同期的に行って検索結果を取得する
+    ///
+    /// `blocking` featureが必要
+    ///
+    /// # Errors
+    ///
+    /// [`search`](Self::search) と同様
+    #[cfg(feature = "blocking")]
+    pub fn search_blocking(&self) -> Result<ResultSet, Error> {
+        crate::client::blocking::Client::new()?.search(self)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Corpus, MockServer};
+
+    /// モックサーバーに対してリクエストを行う
+    async fn mock_search(request: &Request) -> Result<ResultSet, Error> {
+        let server = MockServer::start(Corpus::fixtures()).await.unwrap();
+        server.client()?.search(request).await
+    }
 
     #[tokio::test]
     async fn search_test() {
-        let res = RequestBuilder::default()
+        let request = RequestBuilder::default()
             .crt_date_from(NaiveDate::from_ymd_opt(2000, 1, 1).unwrap())
             .results_num(1)
             .build()
-            .unwrap()
-            .search()
-            .await;
-        res.unwrap();
+            .unwrap();
+        let res = mock_search(&request).await.unwrap();
+        assert_eq!(res.len(), 1);
+    }
+
+    #[test]
+    fn url_test() {
+        let request = RequestBuilder::default()
+            .search_type(SearchType::Reference)
+            .query("question = rust")
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url(),
+            "https://crd.ndl.go.jp/api/refsearch?type=reference&query=question+%3D+rust"
+        );
+        assert_eq!(
+            request.url_with_base("http://127.0.0.1:8080/api/refsearch/"),
+            "http://127.0.0.1:8080/api/refsearch?type=reference&query=question+%3D+rust"
+        );
     }
 
     #[tokio::test]
     async fn search_example_1() {
-        RequestBuilder::default()
-            .search_type("reference")
+        let request = RequestBuilder::default()
+            .search_type(SearchType::Reference)
             .query("question any 読書")
             .build()
-            .unwrap()
-            .search()
-            .await
             .unwrap();
+        mock_search(&request).await.unwrap();
     }
 
     #[tokio::test]
     async fn search_example_2() {
-        RequestBuilder::default()
-            .search_type("reference")
+        let request = RequestBuilder::default()
+            .search_type(SearchType::Reference)
             .results_num(50)
             .query("question any 本 and answer any 村上春樹")
             .build()
-            .unwrap()
-            .search()
-            .await
             .unwrap();
+        mock_search(&request).await.unwrap();
     }
 
     #[tokio::test]
     async fn search_example_3() {
-        RequestBuilder::default()
-            .search_type("reference")
+        let request = RequestBuilder::default()
+            .search_type(SearchType::Reference)
             .query("question any 本 音楽 and solution = 0")
             .crt_date_from("2000-01-01".parse::<NaiveDate>().unwrap())
             .build()
-            .unwrap()
-            .search()
-            .await
+            .unwrap();
+        mock_search(&request).await.unwrap();
+    }
+
+    #[test]
+    fn enum_param_test() {
+        let request = RequestBuilder::default()
+            .search_type(SearchType::Profile)
+            .lib_group(LibGroup::Public)
+            .sort(SortKey::ProKey)
+            .sort_order(SortOrder::Asc)
+            .query("anywhere = 図書館")
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.query_string(),
+            "type=profile&query=anywhere+%3D+%E5%9B%B3%E6%9B%B8%E9%A4%A8&lib-group=public&sort=pro-key&sort_order=asc"
+        );
+        assert_eq!("crt-date".parse::<SortKey>().unwrap(), SortKey::CrtDate);
+        assert_eq!(
+            "foo".parse::<SortOrder>().unwrap_err().to_string(),
+            "invalid value `foo` for SortOrder"
+        );
+    }
+
+    #[test]
+    fn sort_validation_test() {
+        assert!(SortKey::ProKey.is_valid_for(SearchType::Profile));
+        assert!(!SortKey::ProKey.is_valid_for(SearchType::Reference));
+        assert!(!SortKey::CrtDate.is_valid_for(SearchType::Collection));
+        assert!(SortKey::CrtDate.is_valid_for(SearchType::Manual));
+        let err = RequestBuilder::default()
+            .search_type(SearchType::Collection)
+            .sort(SortKey::CrtDate)
+            .query("anywhere = 地図")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sort key `crt-date` is not available for search type `collection`"
+        );
+        for sort in SortKey::VALUES {
+            assert!(sort.is_valid_for(SearchType::All));
+        }
+        RequestBuilder::default()
+            .sort(SortKey::RegId)
+            .query("anywhere = 地図")
+            .build()
             .unwrap();
     }
 
     #[tokio::test]
     async fn simple_search_test() {
-        Request::new("rust").search().await.unwrap();
+        let res = mock_search(&Request::new("rust")).await.unwrap();
+        assert_eq!(res.hit_num, 1);
+    }
+
+    #[test]
+    fn new_escape_test() {
+        let request = Request::new(r#"rust" or anywhere = "go"#);
+        assert_eq!(
+            request.query.as_deref(),
+            Some(r#"anywhere = "rust\" or anywhere = \"go""#)
+        );
+        assert_eq!(
+            crate::cql::parse(request.query.as_deref().unwrap()).unwrap(),
+            Query::equal("anywhere", &[r#"rust" or anywhere = "go"#])
+        );
     }
 }
@@ -1,6 +1,20 @@
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::request::SearchType;
+
+#[cfg(any(test, feature = "mock", feature = "index"))]
+pub(crate) mod fields;
+mod lib_type;
+mod ndc;
+mod reader;
+mod writer;
+
+pub use lib_type::LibType;
+pub use ndc::{Ndc, NdcVersion};
+pub use reader::{AsyncResultReader, ResultHeader, ResultReader};
+use writer::{WriteXml, XmlWriter};
+
 /// 返却結果ルートノード
 ///
 /// 参照: <https://crd.ndl.go.jp/jp/help/general/api_spec_2.html#response>
@@ -25,10 +39,26 @@ pub struct ResultSet {
     results_cd: u32,
 
     /// 返却結果フィールド
+    ///
+    /// ヒット数が `0` の場合は空
+    #[serde(default)]
     pub result: Vec<ResultItem>,
 }
 
 impl ResultSet {
+    /// 返却結果を作成する
+    ///
+    /// [`results_num`](Self::results_num) は `result` の要素数となる
+    pub fn new(hit_num: u32, results_get_position: u32, result: Vec<ResultItem>) -> Self {
+        Self {
+            hit_num,
+            results_get_position,
+            results_num: result.len() as u32,
+            results_cd: 0,
+            result,
+        }
+    }
+
     /// xml形式の文字列から [`ResultSet`] に変換する
     ///
     /// # Errors
@@ -38,11 +68,23 @@ impl ResultSet {
         quick_xml::de::from_str(s)
     }
 
+    /// APIの返却結果と同じ形式のxmlに変換する
+    pub fn to_xml(&self) -> String {
+        let mut w = XmlWriter::default();
+        self.write_xml(&mut w);
+        w.into_string()
+    }
+
     /// 結果の要素数を返す
     pub fn len(&self) -> usize {
         self.result.len()
     }
 
+    /// 結果が空なら [`true`] を返す
+    pub fn is_empty(&self) -> bool {
+        self.result.is_empty()
+    }
+
     /// 結果の要素のイテレータを返す
     pub fn iter(&self) -> impl Iterator<Item = &ResultItem> {
         self.result.iter()
@@ -103,6 +145,7 @@ struct ResultItemWrapper {
 
 /// 返却結果フィールド
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum ResultItem {
     /// レファレンス事例
     Reference(Reference),
@@ -117,6 +160,98 @@ pub enum ResultItem {
     Profile(Profile),
 }
 
+impl ResultItem {
+    /// xml形式の文字列 (`result` 要素) から [`ResultItem`] に変換する
+    ///
+    /// # Errors
+    ///
+    /// xmlの解析に失敗したときエラーを返す
+    pub fn from_xml(s: &str) -> Result<Self, quick_xml::DeError> {
+        quick_xml::de::from_str(s)
+    }
+
+    /// APIの返却結果と同じ形式のxml (`result` 要素) に変換する
+    pub fn to_xml(&self) -> String {
+        let mut w = XmlWriter::default();
+        w.start("result");
+        self.write_xml(&mut w);
+        w.end("result");
+        w.into_string()
+    }
+
+    /// 検索区分
+    pub fn search_type(&self) -> SearchType {
+        match self {
+            Self::Reference(_) => SearchType::Reference,
+            Self::Manual(_) => SearchType::Manual,
+            Self::Collection(_) => SearchType::Collection,
+            Self::Profile(_) => SearchType::Profile,
+        }
+    }
+
+    /// 一意となるキー
+    ///
+    /// 参加館プロファイルは図書館コード, それ以外はシステムID (登録番号)
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Reference(r) => &r.system.sys_id,
+            Self::Manual(m) => &m.system.sys_id,
+            Self::Collection(c) => &c.system.sys_id,
+            Self::Profile(p) => &p.system.lib_id,
+        }
+    }
+
+    /// 表題 (質問, 調べ方テーマ, コレクション名, 図書館名)
+    pub fn title(&self) -> &str {
+        match self {
+            Self::Reference(r) => &r.question,
+            Self::Manual(m) => &m.theme,
+            Self::Collection(c) => &c.col_name,
+            Self::Profile(p) => &p.lib_name,
+        }
+    }
+
+    /// 提供館コード (参加館プロファイルは図書館コード)
+    pub fn lib_id(&self) -> &str {
+        match self {
+            Self::Reference(r) => &r.system.lib_id,
+            Self::Manual(m) => &m.system.lib_id,
+            Self::Collection(c) => &c.system.lib_id,
+            Self::Profile(p) => &p.system.lib_id,
+        }
+    }
+
+    /// 登録日時
+    pub fn reg_date(&self) -> NaiveDateTime {
+        match self {
+            Self::Reference(r) => r.system.reg_date,
+            Self::Manual(m) => m.system.reg_date,
+            Self::Collection(c) => c.system.reg_date,
+            Self::Profile(p) => p.system.reg_date,
+        }
+    }
+
+    /// 最終更新日時
+    pub fn lst_date(&self) -> NaiveDateTime {
+        match self {
+            Self::Reference(r) => r.system.lst_date,
+            Self::Manual(m) => m.system.lst_date,
+            Self::Collection(c) => c.system.lst_date,
+            Self::Profile(p) => p.system.lst_date,
+        }
+    }
+
+    /// 一般公開用詳細表示画面のURL
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Reference(r) => &r.url,
+            Self::Manual(m) => &m.url,
+            Self::Collection(c) => &c.url,
+            Self::Profile(p) => &p.url,
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for ResultItem {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -343,7 +478,7 @@ pub struct Collection {
 #[cfg_attr(test, serde(deny_unknown_fields))]
 pub struct Profile {
     /// 館種コード
-    pub lib_type: String,
+    pub lib_type: LibType,
 
     /// 図書館名（正式）
     pub lib_name: String,
@@ -444,6 +579,20 @@ pub struct Class {
     pub class: String,
 }
 
+impl Class {
+    /// NDCの分類記号
+    ///
+    /// 分類の種類がNDCでない場合や, 分類記号が不正な場合は `None`.
+    /// 版が8〜10以外の場合は版の指定なしとする
+    pub fn ndc(&self) -> Option<Ndc> {
+        if self.class_type != "NDC" {
+            return None;
+        }
+        let version = self.version.as_deref().and_then(|v| v.parse().ok());
+        Ndc::new(&self.class, version).ok()
+    }
+}
+
 /// 参考資料
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
@@ -700,6 +849,24 @@ mod tests {
         assert_eq!(refs[0].question, "質問1");
         assert_eq!(refs[1].reg_id, "002");
         assert_eq!(refs[2].answer, "回答3");
+        assert_eq!(result_set.result[0].title(), "質問1");
+    }
+
+    #[test]
+    fn to_xml_test() {
+        let xml = include_str!("mock/fixtures.xml");
+        let result_set = ResultSet::from_xml(xml).unwrap();
+        assert!(result_set.filter_reference().count() > 0);
+        assert!(result_set.filter_manual().count() > 0);
+        assert!(result_set.filter_collection().count() > 0);
+        assert!(result_set.filter_profile().count() > 0);
+        assert_eq!(
+            ResultSet::from_xml(&result_set.to_xml()).unwrap(),
+            result_set
+        );
+        for item in result_set.iter() {
+            assert_eq!(&ResultItem::from_xml(&item.to_xml()).unwrap(), item);
+        }
     }
 
     #[test]
@@ -747,7 +914,7 @@ mod tests {
             reference.crt_date.unwrap(),
             NaiveDate::from_ymd_opt(2032, 12, 13).unwrap()
         );
-        assert_eq!(reference.solution.unwrap(), true);
+        assert!(reference.solution.unwrap());
         assert_eq!(
             reference.keyword.unwrap(),
             ["キーワード1", "キーワード2", "キーワード3"]
@@ -825,7 +992,7 @@ mod tests {
             manual.crt_date.unwrap(),
             NaiveDate::from_ymd_opt(2033, 2, 13).unwrap()
         );
-        assert_eq!(manual.completion.unwrap(), false);
+        assert!(!manual.completion.unwrap());
         assert_eq!(manual.keyword, None);
         assert_eq!(manual.class.unwrap()[0].class, "219");
         assert_eq!(
@@ -875,7 +1042,7 @@ mod tests {
         assert_eq!(collection.catalog.unwrap(), "蔵書検索にて一覧表示が可能");
         assert_eq!(collection.literature.unwrap(), "ホームページ");
         assert_eq!(collection.number.unwrap(), "75点");
-        assert_eq!(collection.collection_continue.unwrap(), false);
+        assert!(!collection.collection_continue.unwrap());
         assert_eq!(collection.keyword.unwrap(), ["図", "地図"]);
         assert_eq!(
             collection.class.unwrap(),
@@ -927,7 +1094,7 @@ mod tests {
         <url>https://crd.ndl.go.jp/reference/detail?page=pro_view&amp;id=6100012</url>
         </profile>";
         let profile: Profile = from_str(profile).unwrap();
-        assert_eq!(profile.lib_type, "61");
+        assert_eq!(profile.lib_type, LibType::Unknown("61".to_string()));
         assert_eq!(profile.lib_name, "資料館図書室");
         assert_eq!(profile.abbr, "資料館");
         assert_eq!(profile.pro_key, "シリョウカントショシツ");
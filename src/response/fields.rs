@@ -0,0 +1,261 @@
+//! クエリー対象項目ごとの事例の値
+//!
+//! モックサーバーとローカルの全文検索インデックスで, 検索句を評価するために使用する
+
+#[cfg(any(test, feature = "mock"))]
+use crate::cql::{Relation, Term};
+use crate::{
+    cql::{CollectionIndex, ManualIndex, ProfileIndex, ReferenceIndex},
+    response::{Bibl, Class, Collection, Manual, Profile, Reference, ResultItem},
+};
+
+/// 事例のクエリー対象項目の値
+///
+/// 検索区分で使用できない項目なら [`None`] を返す
+pub(crate) fn field<'a>(item: &'a ResultItem, index: &str) -> Option<Field<'a>> {
+    match item {
+        ResultItem::Reference(r) => index.parse().ok().map(|i| reference_field(r, i)),
+        ResultItem::Manual(m) => index.parse().ok().map(|i| manual_field(m, i)),
+        ResultItem::Collection(c) => index.parse().ok().map(|i| collection_field(c, i)),
+        ResultItem::Profile(p) => index.parse().ok().map(|i| profile_field(p, i)),
+    }
+}
+
+/// 検索語と項目の値の一致方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Match {
+    /// 完全一致
+    Exact,
+
+    /// 前方一致
+    Prefix,
+
+    /// 部分一致 (英字の大文字と小文字を区別しない)
+    Partial,
+}
+
+impl Match {
+    #[cfg(any(test, feature = "mock"))]
+    fn is_match(self, value: &str, term: &str) -> bool {
+        match self {
+            Self::Exact => value == term,
+            Self::Prefix => value.starts_with(term),
+            Self::Partial => value.to_lowercase().contains(&term.to_lowercase()),
+        }
+    }
+}
+
+/// 検索対象となる項目の値
+pub(crate) struct Field<'a> {
+    pub(crate) matching: Match,
+    pub(crate) values: Vec<&'a str>,
+}
+
+impl<'a> Field<'a> {
+    fn new(matching: Match) -> Self {
+        Self {
+            matching,
+            values: Vec::new(),
+        }
+    }
+
+    fn text(mut self, value: &'a str) -> Self {
+        self.values.push(value);
+        self
+    }
+
+    fn opt(mut self, value: &'a Option<String>) -> Self {
+        self.values.extend(value.as_deref());
+        self
+    }
+
+    fn list(mut self, values: &'a Option<Vec<String>>) -> Self {
+        self.values
+            .extend(values.iter().flatten().map(String::as_str));
+        self
+    }
+
+    /// コード値とデコード値のどちらでも一致する真偽値
+    fn flag(mut self, value: Option<bool>, t: [&'static str; 2], f: [&'static str; 2]) -> Self {
+        match value {
+            Some(true) => self.values.extend(t),
+            Some(false) => self.values.extend(f),
+            None => {}
+        }
+        self
+    }
+
+    fn extend(mut self, other: Field<'a>) -> Self {
+        self.values.extend(other.values);
+        self
+    }
+
+    #[cfg(any(test, feature = "mock"))]
+    pub(crate) fn is_match(&self, relation: &Relation, terms: &[Term]) -> bool {
+        let hit = |term: &str| self.values.iter().any(|v| self.matching.is_match(v, term));
+        match relation {
+            Relation::All => terms.iter().all(|t| hit(t.as_str())),
+            Relation::Any => terms.iter().any(|t| hit(t.as_str())),
+            Relation::Equal => {
+                let terms: Vec<&str> = terms.iter().map(Term::as_str).collect();
+                hit(&terms.join(" "))
+            }
+        }
+    }
+}
+
+fn ndc<'a>(classes: &'a Option<Vec<Class>>) -> Field<'a> {
+    let mut field = Field::new(Match::Prefix);
+    field.values.extend(
+        classes
+            .iter()
+            .flatten()
+            .filter(|c| c.class_type == "NDC")
+            .map(|c| c.class.as_str()),
+    );
+    field
+}
+
+fn bibl_desc<'a>(bibls: &'a Option<Vec<Bibl>>) -> Field<'a> {
+    let mut field = Field::new(Match::Partial);
+    field.values.extend(
+        bibls
+            .iter()
+            .flatten()
+            .filter_map(|b| b.bibl_desc.as_deref()),
+    );
+    field
+}
+
+fn bibl_isbn<'a>(bibls: &'a Option<Vec<Bibl>>) -> Field<'a> {
+    let mut field = Field::new(Match::Partial);
+    field.values.extend(
+        bibls
+            .iter()
+            .flatten()
+            .filter_map(|b| b.bibl_isbn.as_deref()),
+    );
+    field
+}
+
+/// 全項目 (簡易検索) の値
+fn anywhere<'a, I: Copy + PartialEq>(
+    values: &[I],
+    anywhere: I,
+    flags: &[I],
+    field: impl Fn(I) -> Field<'a>,
+) -> Field<'a> {
+    values
+        .iter()
+        .filter(|i| **i != anywhere && !flags.contains(i))
+        .fold(Field::new(Match::Partial), |acc, i| acc.extend(field(*i)))
+}
+
+fn reference_field(r: &Reference, index: ReferenceIndex) -> Field<'_> {
+    use ReferenceIndex::*;
+    let partial = Field::new(Match::Partial);
+    match index {
+        Anywhere => anywhere(ReferenceIndex::VALUES, Anywhere, &[Solution], |i| {
+            reference_field(r, i)
+        }),
+        Question => partial.text(&r.question),
+        RegId => Field::new(Match::Prefix).text(&r.reg_id),
+        Answer => partial.text(&r.answer),
+        Solution => {
+            Field::new(Match::Exact).flag(r.solution, ["0", "resolved"], ["1", "unresolved"])
+        }
+        Keyword => partial.list(&r.keyword),
+        Ndc => ndc(&r.class),
+        ResType => partial.opt(&r.res_type),
+        ConType => partial.opt(&r.con_type),
+        BiblDesc => bibl_desc(&r.bibl),
+        BiblIsbn => bibl_isbn(&r.bibl),
+        AnsProc => partial.opt(&r.ans_proc),
+        Referral => partial.list(&r.referral),
+        PreRes => partial.opt(&r.pre_res),
+        Note => partial.opt(&r.note),
+        PtnType => partial.opt(&r.ptn_type),
+        Contri => partial.list(&r.contri),
+        SysId => Field::new(Match::Exact).text(&r.system.sys_id),
+        LibName => partial.text(&r.system.lib_name),
+    }
+}
+
+fn manual_field(m: &Manual, index: ManualIndex) -> Field<'_> {
+    use ManualIndex::*;
+    let partial = Field::new(Match::Partial);
+    match index {
+        Anywhere => anywhere(ManualIndex::VALUES, Anywhere, &[Completion], |i| {
+            manual_field(m, i)
+        }),
+        Theme => partial.text(&m.theme),
+        RegId => Field::new(Match::Prefix).text(&m.reg_id),
+        Guide => partial.text(&m.guide),
+        Completion => {
+            Field::new(Match::Exact).flag(m.completion, ["0", "complete"], ["1", "incomplete"])
+        }
+        Keyword => partial.list(&m.keyword),
+        Ndc => ndc(&m.class),
+        BiblDesc => bibl_desc(&m.bibl),
+        BiblIsbn => bibl_isbn(&m.bibl),
+        Note => partial.opt(&m.note),
+        SysId => Field::new(Match::Exact).text(&m.system.sys_id),
+        LibName => partial.text(&m.system.lib_name),
+    }
+}
+
+fn collection_field(c: &Collection, index: CollectionIndex) -> Field<'_> {
+    use CollectionIndex::*;
+    let partial = Field::new(Match::Partial);
+    match index {
+        Anywhere => anywhere(CollectionIndex::VALUES, Anywhere, &[Continue], |i| {
+            collection_field(c, i)
+        }),
+        ColName => partial.text(&c.col_name).text(&c.pro_key),
+        RegId => Field::new(Match::Prefix).text(&c.reg_id),
+        Outline => partial.text(&c.outline),
+        Origin => partial.opt(&c.origin),
+        Restriction => partial.opt(&c.restriction),
+        Catalog => partial.opt(&c.catalog),
+        Literature => partial.opt(&c.literature),
+        Number => partial.opt(&c.number),
+        Continue => Field::new(Match::Exact).flag(
+            c.collection_continue,
+            ["0", "continue"],
+            ["1", "discontinued"],
+        ),
+        Keyword => partial.list(&c.keyword),
+        Ndc => ndc(&c.class),
+        Note => partial.opt(&c.note),
+        SysId => Field::new(Match::Exact).text(&c.system.sys_id),
+        LibName => partial.text(&c.system.lib_name),
+    }
+}
+
+fn profile_field(p: &Profile, index: ProfileIndex) -> Field<'_> {
+    use ProfileIndex::*;
+    let partial = Field::new(Match::Partial);
+    match index {
+        Anywhere => anywhere(ProfileIndex::VALUES, Anywhere, &[LibType], |i| {
+            profile_field(p, i)
+        }),
+        LibType => {
+            // コード値とデコード値のどちらでも一致する
+            let mut field = Field::new(Match::Exact).text(p.lib_type.code());
+            field.values.extend(p.lib_type.label());
+            field
+        }
+        LibName => partial.text(&p.lib_name).text(&p.abbr).text(&p.pro_key),
+        Address => partial
+            .text(&p.add_pref)
+            .text(&p.add_city)
+            .text(&p.add_street),
+        OpenInfo => partial.opt(&p.open_info),
+        Restriction => partial.opt(&p.restriction),
+        Outline => partial.opt(&p.outline),
+        Feature => partial.opt(&p.feature),
+        Notes => partial.opt(&p.notes),
+        Access => partial.opt(&p.access),
+        Isil => partial.opt(&p.isil),
+    }
+}
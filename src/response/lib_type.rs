@@ -0,0 +1,237 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::request::{InvalidParamError, LibGroup};
+
+macro_rules! lib_types {
+    (
+        $(
+            $(#[$variant_meta:meta])*
+            $variant:ident => ($code:literal, $label:literal, $group:ident),
+        )*
+    ) => {
+        /// 館種
+        ///
+        /// 参加館プロファイルの館種コード. 未知のコードは [`LibType::Unknown`] となる
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum LibType {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )*
+
+            /// 未知のコード
+            Unknown(String),
+        }
+
+        impl LibType {
+            /// 既知の全ての館種
+            pub const VALUES: &'static [Self] = &[$(Self::$variant),*];
+
+            /// コードから作成する
+            pub fn from_code(code: &str) -> Self {
+                match code {
+                    $($code => Self::$variant,)*
+                    _ => Self::Unknown(code.to_string()),
+                }
+            }
+
+            /// 名称から作成する
+            ///
+            /// 例: `公共図書館(市立・特別区立)`
+            pub fn from_label(label: &str) -> Option<Self> {
+                match label {
+                    $($label => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// コード
+            pub fn code(&self) -> &str {
+                match self {
+                    $(Self::$variant => $code,)*
+                    Self::Unknown(code) => code,
+                }
+            }
+
+            /// 名称
+            ///
+            /// 未知のコードの場合は `None`
+            pub fn label(&self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => Some($label),)*
+                    Self::Unknown(_) => None,
+                }
+            }
+
+            /// 検索対象 ([`Request::lib_group`](crate::request::Request::lib_group)) の種別
+            ///
+            /// 未知のコードの場合はコードの1桁目から求める
+            pub fn lib_group(&self) -> Option<LibGroup> {
+                match self {
+                    $(Self::$variant => Some(LibGroup::$group),)*
+                    Self::Unknown(code) => match code.as_bytes().first()? {
+                        b'1' => Some(LibGroup::Ndl),
+                        b'2' => Some(LibGroup::Public),
+                        b'3' => Some(LibGroup::Academic),
+                        b'4' => Some(LibGroup::Special),
+                        b'5' => Some(LibGroup::School),
+                        b'9' => Some(LibGroup::Archives),
+                        _ => None,
+                    },
+                }
+            }
+        }
+    };
+}
+
+lib_types! {
+    /// 国立国会図書館(東京本館)
+    NdlTokyo => ("11", "国立国会図書館(東京本館)", Ndl),
+
+    /// 国立国会図書館(関西館)
+    NdlKansai => ("12", "国立国会図書館(関西館)", Ndl),
+
+    /// 国立国会図書館(国際子ども図書館)
+    NdlChildren => ("13", "国立国会図書館(国際子ども図書館)", Ndl),
+
+    /// 国立国会図書館(支部図書館)
+    NdlBranch => ("14", "国立国会図書館(支部図書館)", Ndl),
+
+    /// 公共図書館(都道府県立)
+    PublicPrefectural => ("21", "公共図書館(都道府県立)", Public),
+
+    /// 公共図書館(政令都市立)
+    PublicDesignatedCity => ("22", "公共図書館(政令都市立)", Public),
+
+    /// 公共図書館(市立・特別区立)
+    PublicMunicipal => ("23", "公共図書館(市立・特別区立)", Public),
+
+    /// 公共図書館(町村立)
+    PublicTownVillage => ("24", "公共図書館(町村立)", Public),
+
+    /// 大学図書館(国立大学)
+    AcademicNational => ("31", "大学図書館(国立大学)", Academic),
+
+    /// 大学図書館(公立大学)
+    AcademicPublic => ("32", "大学図書館(公立大学)", Academic),
+
+    /// 大学図書館(私立大学)
+    AcademicPrivate => ("33", "大学図書館(私立大学)", Academic),
+
+    /// 大学図書館(高等専門)
+    AcademicTechnical => ("35", "大学図書館(高等専門)", Academic),
+
+    /// 専門図書館(国公立)
+    SpecialGovernment => ("41", "専門図書館(国公立)", Special),
+
+    /// 専門図書館(公益法人)
+    SpecialPublicInterest => ("42", "専門図書館(公益法人)", Special),
+
+    /// 専門図書館(企業)
+    SpecialCorporate => ("43", "専門図書館(企業)", Special),
+
+    /// 専門図書館(その他)
+    SpecialOther => ("44", "専門図書館(その他)", Special),
+
+    /// 学校図書館(高等学校)
+    SchoolHigh => ("51", "学校図書館(高等学校)", School),
+
+    /// 学校図書館(中学校)
+    SchoolJuniorHigh => ("52", "学校図書館(中学校)", School),
+
+    /// 学校図書館(小学校)
+    SchoolElementary => ("53", "学校図書館(小学校)", School),
+
+    /// 学校図書館(その他)
+    SchoolOther => ("54", "学校図書館(その他)", School),
+
+    /// アーカイブズ
+    Archives => ("90", "アーカイブズ", Archives),
+}
+
+impl Display for LibType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for LibType {
+    type Err = InvalidParamError;
+
+    /// コードまたは名称から作成する
+    ///
+    /// 数字のみの未知のコードは [`LibType::Unknown`] とする
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(lib_type) = Self::from_label(s) {
+            return Ok(lib_type);
+        }
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidParamError {
+                param: "LibType",
+                value: s.to_string(),
+            });
+        }
+        Ok(Self::from_code(s))
+    }
+}
+
+impl Serialize for LibType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for LibType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Ok(Self::from_code(&code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lib_type_test() {
+        for lib_type in LibType::VALUES {
+            assert_eq!(&LibType::from_code(lib_type.code()), lib_type);
+            assert_eq!(
+                LibType::from_label(lib_type.label().unwrap()).as_ref(),
+                Some(lib_type)
+            );
+            assert!(lib_type.lib_group().is_some());
+        }
+        assert_eq!(LibType::from_code("23"), LibType::PublicMunicipal);
+        assert_eq!(
+            LibType::PublicMunicipal.label(),
+            Some("公共図書館(市立・特別区立)")
+        );
+        assert_eq!(
+            LibType::AcademicPrivate.lib_group(),
+            Some(LibGroup::Academic)
+        );
+        assert_eq!(LibType::Archives.to_string(), "90");
+    }
+
+    #[test]
+    fn unknown_test() {
+        let lib_type = LibType::from_code("61");
+        assert_eq!(lib_type, LibType::Unknown("61".to_string()));
+        assert_eq!(lib_type.code(), "61");
+        assert_eq!(lib_type.label(), None);
+        assert_eq!(lib_type.lib_group(), None);
+        assert_eq!(LibType::from_code("25").lib_group(), Some(LibGroup::Public));
+    }
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!("11".parse(), Ok(LibType::NdlTokyo));
+        assert_eq!("アーカイブズ".parse(), Ok(LibType::Archives));
+        assert_eq!("61".parse(), Ok(LibType::Unknown("61".to_string())));
+        assert!("図書館".parse::<LibType>().is_err());
+        assert!("".parse::<LibType>().is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::request::InvalidParamError;
+
+param_enum! {
+    /// NDC (日本十進分類法) の版
+    pub enum NdcVersion {
+        /// 新訂8版
+        V8 => "8",
+
+        /// 新訂9版
+        V9 => "9",
+
+        /// 新訂10版
+        V10 => "10",
+    }
+}
+
+/// NDC (日本十進分類法) の分類記号
+///
+/// 例: `910.268`
+///
+/// 先頭の1桁が類, 2桁が綱, 3桁が目を表す
+///
+/// # Example
+///
+/// ```
+/// use crd_api::response::{Ndc, NdcVersion};
+///
+/// let ndc = Ndc::new("910.268", Some(NdcVersion::V9)).unwrap();
+/// assert_eq!(ndc.class().as_str(), "9");
+/// assert_eq!(ndc.division().unwrap().as_str(), "91");
+/// assert_eq!(ndc.section().unwrap().as_str(), "910");
+/// assert_eq!(ndc.class_label(), "文学");
+/// assert_eq!(ndc.division_label(), Some("日本文学"));
+/// assert!(ndc.starts_with("91"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ndc {
+    notation: String,
+    version: Option<NdcVersion>,
+}
+
+impl Ndc {
+    /// 分類記号と版から作成する
+    ///
+    /// 分類記号は1〜3桁の数字, または3桁の数字に小数点以下の数字が続くもの
+    pub fn new(notation: &str, version: Option<NdcVersion>) -> Result<Self, InvalidParamError> {
+        let notation = notation.trim();
+        let (main, sub) = match notation.split_once('.') {
+            Some((main, sub)) => (main, Some(sub)),
+            None => (notation, None),
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let is_valid = match sub {
+            Some(sub) => main.len() == 3 && is_digits(main) && is_digits(sub),
+            None => main.len() <= 3 && is_digits(main),
+        };
+        if !is_valid {
+            return Err(InvalidParamError {
+                param: "Ndc",
+                value: notation.to_string(),
+            });
+        }
+        Ok(Self {
+            notation: notation.to_string(),
+            version,
+        })
+    }
+
+    /// 分類記号
+    pub fn as_str(&self) -> &str {
+        &self.notation
+    }
+
+    /// 版
+    pub fn version(&self) -> Option<NdcVersion> {
+        self.version
+    }
+
+    /// 類 (第1次区分)
+    pub fn class(&self) -> Ndc {
+        self.truncate(1).unwrap()
+    }
+
+    /// 綱 (第2次区分)
+    ///
+    /// 分類記号が1桁の場合は `None`
+    pub fn division(&self) -> Option<Ndc> {
+        self.truncate(2)
+    }
+
+    /// 目 (第3次区分)
+    ///
+    /// 分類記号が3桁未満の場合は `None`
+    pub fn section(&self) -> Option<Ndc> {
+        self.truncate(3)
+    }
+
+    /// 類の名称
+    pub fn class_label(&self) -> &'static str {
+        CLASSES[self.digit(0)]
+    }
+
+    /// 綱の名称
+    ///
+    /// 分類記号が1桁の場合は `None`
+    pub fn division_label(&self) -> Option<&'static str> {
+        self.division()
+            .map(|_| DIVISIONS[self.digit(0) * 10 + self.digit(1)])
+    }
+
+    /// 分類記号が `prefix` で始まるか
+    ///
+    /// APIの `ndc` の前方一致検索と同じく, 版は考慮しない
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.notation.starts_with(prefix)
+    }
+
+    /// `other` がこの分類の下位 (または同一) の分類か
+    ///
+    /// 両方の版が分かっていて異なる場合は `false`
+    pub fn contains(&self, other: &Ndc) -> bool {
+        let same_version = match (self.version, other.version) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        same_version && other.starts_with(&self.notation)
+    }
+
+    fn truncate(&self, len: usize) -> Option<Ndc> {
+        (self.notation.len() >= len).then(|| Self {
+            notation: self.notation[..len].to_string(),
+            version: self.version,
+        })
+    }
+
+    fn digit(&self, i: usize) -> usize {
+        (self.notation.as_bytes()[i] - b'0') as usize
+    }
+}
+
+impl Display for Ndc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.notation)
+    }
+}
+
+impl FromStr for Ndc {
+    type Err = InvalidParamError;
+
+    /// 版の指定なしで作成する
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s, None)
+    }
+}
+
+/// 類の名称 (新訂10版)
+const CLASSES: [&str; 10] = [
+    "総記",
+    "哲学",
+    "歴史",
+    "社会科学",
+    "自然科学",
+    "技術",
+    "産業",
+    "芸術",
+    "言語",
+    "文学",
+];
+
+/// 綱の名称 (新訂10版)
+///
+/// 8版, 9版とは一部の名称が異なるが, 区分は共通のため全ての版で用いる
+const DIVISIONS: [&str; 100] = [
+    "総記",
+    "図書館．図書館情報学",
+    "図書．書誌学",
+    "百科事典．用語索引",
+    "一般論文集．一般講演集．雑著",
+    "逐次刊行物．一般年鑑",
+    "団体．博物館",
+    "ジャーナリズム．新聞",
+    "叢書．全集．選集",
+    "貴重書．郷土資料．その他の特別コレクション",
+    "哲学",
+    "哲学各論",
+    "東洋思想",
+    "西洋哲学",
+    "心理学",
+    "倫理学．道徳",
+    "宗教",
+    "神道",
+    "仏教",
+    "キリスト教．ユダヤ教",
+    "歴史．世界史．文化史",
+    "日本史",
+    "アジア史．東洋史",
+    "ヨーロッパ史．西洋史",
+    "アフリカ史",
+    "北アメリカ史",
+    "南アメリカ史",
+    "オセアニア史．両極地方史",
+    "伝記",
+    "地理．地誌．紀行",
+    "社会科学",
+    "政治",
+    "法律",
+    "経済",
+    "財政",
+    "統計",
+    "社会",
+    "教育",
+    "風俗習慣．民俗学．民族学",
+    "国防．軍事",
+    "自然科学",
+    "数学",
+    "物理学",
+    "化学",
+    "天文学．宇宙科学",
+    "地球科学．地学",
+    "生物科学．一般生物学",
+    "植物学",
+    "動物学",
+    "医学．薬学",
+    "技術．工学",
+    "建設工学．土木工学",
+    "建築学",
+    "機械工学．原子力工学",
+    "電気工学",
+    "海洋工学．船舶工学．兵器．軍事工学",
+    "金属工学．鉱山工学",
+    "化学工業",
+    "製造工業",
+    "家政学．生活科学",
+    "産業",
+    "農業",
+    "園芸．造園",
+    "蚕糸業",
+    "畜産業．獣医学",
+    "林業．狩猟",
+    "水産業",
+    "商業",
+    "運輸．交通．観光事業",
+    "通信事業",
+    "芸術．美術",
+    "彫刻．オブジェ",
+    "絵画．書道",
+    "版画．印章．篆刻．印譜",
+    "写真．印刷",
+    "工芸",
+    "音楽．舞踊．バレエ",
+    "演劇．映画．大衆芸能",
+    "スポーツ．体育",
+    "諸芸．娯楽",
+    "言語",
+    "日本語",
+    "中国語．その他の東洋の諸言語",
+    "英語",
+    "ドイツ語．その他のゲルマン諸語",
+    "フランス語．プロバンス語",
+    "スペイン語．ポルトガル語",
+    "イタリア語．その他のロマンス諸語",
+    "ロシア語．その他のスラブ諸語",
+    "その他の諸言語",
+    "文学",
+    "日本文学",
+    "中国文学．その他の東洋文学",
+    "英米文学",
+    "ドイツ文学．その他のゲルマン文学",
+    "フランス文学．プロバンス文学",
+    "スペイン文学．ポルトガル文学",
+    "イタリア文学．その他のロマンス文学",
+    "ロシア・ソビエト文学．その他のスラブ文学",
+    "その他の諸言語文学",
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        mock::Corpus,
+        response::{Class, ResultItem},
+    };
+
+    #[test]
+    fn parse_test() {
+        let ndc: Ndc = "910.268".parse().unwrap();
+        assert_eq!(ndc.as_str(), "910.268");
+        assert_eq!(ndc.version(), None);
+        assert_eq!(" 91 ".parse::<Ndc>().unwrap().as_str(), "91");
+        for s in ["", "9102", "91.2", "910.", ".268", "A10", "910.2a"] {
+            assert!(s.parse::<Ndc>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn hierarchy_test() {
+        let ndc = Ndc::new("007.64", Some(NdcVersion::V10)).unwrap();
+        assert_eq!(ndc.class(), Ndc::new("0", Some(NdcVersion::V10)).unwrap());
+        assert_eq!(ndc.division().unwrap().as_str(), "00");
+        assert_eq!(ndc.section().unwrap().as_str(), "007");
+        assert_eq!(ndc.class_label(), "総記");
+        assert_eq!(ndc.division_label(), Some("総記"));
+
+        let ndc: Ndc = "7".parse().unwrap();
+        assert_eq!(ndc.division(), None);
+        assert_eq!(ndc.section(), None);
+        assert_eq!(ndc.class_label(), "芸術");
+        assert_eq!(ndc.division_label(), None);
+    }
+
+    #[test]
+    fn prefix_test() {
+        let ndc = Ndc::new("910.268", Some(NdcVersion::V9)).unwrap();
+        assert!(ndc.starts_with("9"));
+        assert!(ndc.starts_with("910.2"));
+        assert!(!ndc.starts_with("92"));
+
+        assert!(ndc.division().unwrap().contains(&ndc));
+        assert!("91".parse::<Ndc>().unwrap().contains(&ndc));
+        assert!(!ndc.contains(&ndc.section().unwrap()));
+        assert!(!Ndc::new("91", Some(NdcVersion::V10))
+            .unwrap()
+            .contains(&ndc));
+    }
+
+    #[test]
+    fn group_test() {
+        let corpus = Corpus::fixtures();
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        for item in corpus.items() {
+            let classes = match item {
+                ResultItem::Reference(r) => &r.class,
+                ResultItem::Manual(m) => &m.class,
+                ResultItem::Collection(c) => &c.class,
+                ResultItem::Profile(_) => continue,
+            };
+            for ndc in classes.iter().flatten().filter_map(Class::ndc) {
+                groups.entry(ndc.class_label()).or_default().push(item.id());
+            }
+        }
+        assert_eq!(groups["文学"], ["1000000002", "3000000002"]);
+        assert_eq!(groups["芸術"], ["1000000002"]);
+    }
+}
@@ -0,0 +1,403 @@
+use std::io::BufRead;
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    DeError, Reader, Writer,
+};
+use tokio::io::AsyncBufRead;
+
+use crate::error::{ApiErrors, Error};
+
+use super::ResultItem;
+
+/// 返却結果のヘッダー
+///
+/// [`ResultSet`](super::ResultSet) のうち返却結果フィールド以外の項目
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultHeader {
+    /// ヒット数
+    pub hit_num: u32,
+
+    /// 検索開始位置
+    pub results_get_position: u32,
+
+    /// 検索結果返却件数
+    pub results_num: u32,
+}
+
+/// 返却結果を1件ずつ読み込むリーダー
+///
+/// 返却結果全体をメモリに読み込まずに [`ResultItem`] を順に返す
+///
+/// # Example
+///
+/// ```
+/// use crd_api::response::ResultReader;
+///
+/// let xml = "<result_set>
+///     <hit_num>0</hit_num>
+///     <results_get_position>1</results_get_position>
+///     <results_num>0</results_num>
+///     <results_cd>0</results_cd>
+/// </result_set>";
+/// let mut reader = ResultReader::new(xml.as_bytes()).unwrap();
+/// assert_eq!(reader.header().hit_num, 0);
+/// assert!(reader.next().is_none());
+/// ```
+pub struct ResultReader<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    collector: Collector,
+    header: ResultHeader,
+}
+
+impl<R: BufRead> ResultReader<R> {
+    /// ヘッダーを読み込んでリーダーを作成する
+    ///
+    /// # Errors
+    ///
+    /// 以下の場合エラーを返す
+    ///
+    /// - XMLの読み込みや解析に失敗したとき
+    /// - APIがエラーを返したとき
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = Reader::from_reader(reader);
+        let mut buf = Vec::new();
+        let mut collector = Collector::default();
+        while !collector.header_complete {
+            let event = reader.read_event_into(&mut buf).map_err(DeError::from)?;
+            collector.feed(event)?;
+            buf.clear();
+        }
+        let header = collector.header()?;
+        Ok(Self {
+            reader,
+            buf,
+            collector,
+            header,
+        })
+    }
+
+    /// 返却結果のヘッダー
+    pub fn header(&self) -> &ResultHeader {
+        &self.header
+    }
+
+    fn read_item(&mut self) -> Result<Option<ResultItem>, Error> {
+        loop {
+            let event = self
+                .reader
+                .read_event_into(&mut self.buf)
+                .map_err(DeError::from)?;
+            let step = self.collector.feed(event)?;
+            self.buf.clear();
+            match step {
+                Step::Continue => {}
+                Step::Item(item) => return Ok(Some(*item)),
+                Step::End => return Ok(None),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ResultReader<R> {
+    type Item = Result<ResultItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.collector.finished {
+            return None;
+        }
+        let item = self.read_item();
+        if !matches!(item, Ok(Some(_))) {
+            self.collector.finished = true;
+        }
+        item.transpose()
+    }
+}
+
+/// 返却結果を1件ずつ非同期に読み込むリーダー
+///
+/// [`ResultReader`] の非同期版
+pub struct AsyncResultReader<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    collector: Collector,
+    header: ResultHeader,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncResultReader<R> {
+    /// ヘッダーを読み込んでリーダーを作成する
+    ///
+    /// # Errors
+    ///
+    /// [`ResultReader::new`] と同様
+    pub async fn new(reader: R) -> Result<Self, Error> {
+        let mut reader = Reader::from_reader(reader);
+        let mut buf = Vec::new();
+        let mut collector = Collector::default();
+        while !collector.header_complete {
+            let event = reader
+                .read_event_into_async(&mut buf)
+                .await
+                .map_err(DeError::from)?;
+            collector.feed(event)?;
+            buf.clear();
+        }
+        let header = collector.header()?;
+        Ok(Self {
+            reader,
+            buf,
+            collector,
+            header,
+        })
+    }
+
+    /// 返却結果のヘッダー
+    pub fn header(&self) -> &ResultHeader {
+        &self.header
+    }
+
+    /// 次の返却結果を読み込む
+    ///
+    /// 全て読み込んだ後, またはエラーが発生した後は [`None`] を返す
+    pub async fn next_item(&mut self) -> Option<Result<ResultItem, Error>> {
+        if self.collector.finished {
+            return None;
+        }
+        let item = self.read_item().await;
+        if !matches!(item, Ok(Some(_))) {
+            self.collector.finished = true;
+        }
+        item.transpose()
+    }
+
+    /// 残りの返却結果のストリームに変換する
+    pub fn into_stream(self) -> impl futures::Stream<Item = Result<ResultItem, Error>> {
+        futures::stream::unfold(self, |mut reader| async move {
+            let item = reader.next_item().await?;
+            Some((item, reader))
+        })
+    }
+
+    async fn read_item(&mut self) -> Result<Option<ResultItem>, Error> {
+        loop {
+            let event = self
+                .reader
+                .read_event_into_async(&mut self.buf)
+                .await
+                .map_err(DeError::from)?;
+            let step = self.collector.feed(event)?;
+            self.buf.clear();
+            match step {
+                Step::Continue => {}
+                Step::Item(item) => return Ok(Some(*item)),
+                Step::End => return Ok(None),
+            }
+        }
+    }
+}
+
+enum Step {
+    Continue,
+    Item(Box<ResultItem>),
+    End,
+}
+
+/// 読み込んだイベントからヘッダーと返却結果を組み立てる
+#[derive(Default)]
+struct Collector {
+    depth: usize,
+    field: Option<String>,
+    hit_num: Option<u32>,
+    results_get_position: Option<u32>,
+    results_num: Option<u32>,
+    header_complete: bool,
+    finished: bool,
+    capture: Option<Capture>,
+}
+
+/// 読み込み中の `result` または `err_list` 要素
+struct Capture {
+    depth: usize,
+    is_err_list: bool,
+    writer: Writer<Vec<u8>>,
+}
+
+impl Collector {
+    fn header(&self) -> Result<ResultHeader, Error> {
+        let missing = |name| DeError::Custom(format!("missing field `{name}`"));
+        Ok(ResultHeader {
+            hit_num: self.hit_num.ok_or_else(|| missing("hit_num"))?,
+            results_get_position: self
+                .results_get_position
+                .ok_or_else(|| missing("results_get_position"))?,
+            results_num: self.results_num.ok_or_else(|| missing("results_num"))?,
+        })
+    }
+
+    fn feed(&mut self, event: Event<'_>) -> Result<Step, Error> {
+        if let Some(capture) = &mut self.capture {
+            match &event {
+                Event::Start(_) => capture.depth += 1,
+                Event::End(_) => capture.depth -= 1,
+                Event::Eof => return Err(DeError::UnexpectedEof.into()),
+                _ => {}
+            }
+            capture
+                .writer
+                .write_event(event)
+                .map_err(|e| DeError::Custom(e.to_string()))?;
+            if capture.depth > 0 {
+                return Ok(Step::Continue);
+            }
+            let capture = self.capture.take().unwrap();
+            self.depth -= 1;
+            let xml = String::from_utf8(capture.writer.into_inner())
+                .map_err(|e| DeError::Custom(e.to_string()))?;
+            if capture.is_err_list {
+                let errors = ApiErrors::from_xml(&format!(
+                    "<result_set><results_cd>1</results_cd>{xml}</result_set>"
+                ))?;
+                return Err(errors.into());
+            }
+            return Ok(Step::Item(Box::new(quick_xml::de::from_str(&xml)?)));
+        }
+        match event {
+            Event::Start(e) => {
+                self.depth += 1;
+                if self.depth == 2 {
+                    self.start_child(e);
+                }
+            }
+            Event::Text(e) if self.depth == 2 => {
+                let Some(field) = &self.field else {
+                    return Ok(Step::Continue);
+                };
+                let text = String::from_utf8_lossy(&e);
+                let value = || {
+                    text.trim().parse::<u32>().map_err(|_| {
+                        DeError::Custom(format!("failed to parse `{}` in `{field}`", text.trim()))
+                    })
+                };
+                match field.as_str() {
+                    "hit_num" => self.hit_num = Some(value()?),
+                    "results_get_position" => self.results_get_position = Some(value()?),
+                    "results_num" => self.results_num = Some(value()?),
+                    _ => {}
+                }
+            }
+            Event::End(_) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.field = None;
+                if self.depth == 0 {
+                    self.header_complete = true;
+                    return Ok(Step::End);
+                }
+            }
+            Event::Eof => {
+                self.header_complete = true;
+                return Ok(Step::End);
+            }
+            _ => {}
+        }
+        Ok(Step::Continue)
+    }
+
+    fn start_child(&mut self, e: BytesStart<'_>) {
+        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+        match name.as_str() {
+            "result" | "err_list" => {
+                self.header_complete |= name == "result";
+                let mut writer = Writer::new(Vec::new());
+                let is_err_list = name == "err_list";
+                // 開始タグは書き込みに失敗しない
+                writer.write_event(Event::Start(e)).unwrap();
+                self.capture = Some(Capture {
+                    depth: 1,
+                    is_err_list,
+                    writer,
+                });
+            }
+            _ => self.field = Some(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tests::page_xml;
+
+    #[test]
+    fn result_reader_test() {
+        let xml = page_xml("/?results_num=3", 5);
+        let mut reader = ResultReader::new(xml.as_bytes()).unwrap();
+        assert_eq!(
+            reader.header(),
+            &ResultHeader {
+                hit_num: 5,
+                results_get_position: 1,
+                results_num: 3,
+            }
+        );
+        let items: Vec<ResultItem> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(items.len(), 3);
+        let ResultItem::Reference(reference) = &items[2] else {
+            panic!("expected reference");
+        };
+        assert_eq!(reference.question, "質問3");
+        assert_eq!(
+            reference.url,
+            "https://crd.ndl.go.jp/reference/detail?page=ref_view&id=3"
+        );
+        assert!(reader.next().is_none());
+        assert_eq!(
+            super::super::ResultSet::from_xml(&xml).unwrap().result,
+            items
+        );
+    }
+
+    #[test]
+    fn result_reader_error_test() {
+        let xml = "<result_set>
+        <results_cd>1</results_cd>
+        <err_list>
+            <err_item>
+                <err_code>0503</err_code>
+                <err_fld>ndc</err_fld>
+                <err_msg>【ndc】に使用できない値が指定されています。</err_msg>
+            </err_item>
+        </err_list>
+        </result_set>";
+        let Err(Error::Api(errors)) = ResultReader::new(xml.as_bytes()) else {
+            panic!("expected api error");
+        };
+        assert_eq!(errors.iter().next().unwrap().field(), Some("ndc"));
+    }
+
+    #[test]
+    fn result_reader_invalid_item_test() {
+        let xml = "<result_set>
+        <hit_num>2</hit_num>
+        <results_get_position>1</results_get_position>
+        <results_num>2</results_num>
+        <results_cd>0</results_cd>
+        <result><unknown/></result>
+        <result><unknown/></result>
+        </result_set>";
+        let mut reader = ResultReader::new(xml.as_bytes()).unwrap();
+        assert!(matches!(reader.next(), Some(Err(Error::De(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn async_result_reader_test() {
+        use futures::TryStreamExt;
+
+        let xml = page_xml("/?results_get_position=3&results_num=10", 5);
+        let reader = AsyncResultReader::new(xml.as_bytes()).await.unwrap();
+        assert_eq!(reader.header().results_get_position, 3);
+        let items: Vec<ResultItem> = reader.into_stream().try_collect().await.unwrap();
+        assert_eq!(items.len(), 3);
+    }
+}
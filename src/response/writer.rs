@@ -0,0 +1,248 @@
+use std::fmt::Write;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use quick_xml::escape::escape;
+
+use super::{
+    Bibl, Class, Collection, LibSystem, Manual, Profile, Reference, ResultItem, ResultSet, System,
+};
+
+/// APIの返却結果と同じ形式のXMLを書き込む
+pub(super) trait WriteXml {
+    fn write_xml(&self, w: &mut XmlWriter);
+}
+
+#[derive(Default)]
+pub(super) struct XmlWriter(String);
+
+impl XmlWriter {
+    pub(super) fn into_string(self) -> String {
+        self.0
+    }
+
+    pub(super) fn start(&mut self, name: &str) {
+        write!(self.0, "<{name}>").unwrap();
+    }
+
+    pub(super) fn end(&mut self, name: &str) {
+        write!(self.0, "</{name}>").unwrap();
+    }
+
+    fn text(&mut self, name: &str, value: &str) {
+        write!(self.0, "<{name}>{}</{name}>", escape(value)).unwrap();
+    }
+
+    fn opt(&mut self, name: &str, value: &Option<String>) {
+        if let Some(value) = value {
+            self.text(name, value);
+        }
+    }
+
+    fn list(&mut self, name: &str, values: &Option<Vec<String>>) {
+        for value in values.iter().flatten() {
+            self.text(name, value);
+        }
+    }
+
+    fn bool(&mut self, name: &str, value: Option<bool>, t: &str, f: &str) {
+        if let Some(value) = value {
+            self.text(name, if value { t } else { f });
+        }
+    }
+
+    fn date(&mut self, name: &str, value: Option<NaiveDate>) {
+        if let Some(value) = value {
+            self.text(name, &value.format("%Y%m%d").to_string());
+        }
+    }
+
+    fn datetime(&mut self, name: &str, value: NaiveDateTime) {
+        self.text(name, &value.format("%Y%m%d%H%M%S").to_string());
+    }
+
+    fn classes(&mut self, values: &Option<Vec<Class>>) {
+        for class in values.iter().flatten() {
+            class.write_xml(self);
+        }
+    }
+
+    fn bibls(&mut self, values: &Option<Vec<Bibl>>) {
+        for bibl in values.iter().flatten() {
+            bibl.write_xml(self);
+        }
+    }
+}
+
+impl WriteXml for ResultSet {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.0.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        w.start("result_set");
+        w.text("hit_num", &self.hit_num.to_string());
+        w.text(
+            "results_get_position",
+            &self.results_get_position.to_string(),
+        );
+        w.text("results_num", &self.results_num.to_string());
+        w.text("results_cd", &self.results_cd.to_string());
+        for item in &self.result {
+            w.start("result");
+            item.write_xml(w);
+            w.end("result");
+        }
+        w.end("result_set");
+    }
+}
+
+impl WriteXml for ResultItem {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        match self {
+            Self::Reference(r) => r.write_xml(w),
+            Self::Manual(m) => m.write_xml(w),
+            Self::Collection(c) => c.write_xml(w),
+            Self::Profile(p) => p.write_xml(w),
+        }
+    }
+}
+
+impl WriteXml for Reference {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("reference");
+        w.text("question", &self.question);
+        w.text("reg-id", &self.reg_id);
+        w.text("answer", &self.answer);
+        w.date("crt-date", self.crt_date);
+        w.bool("solution", self.solution, "0", "1");
+        w.list("keyword", &self.keyword);
+        w.classes(&self.class);
+        w.opt("res-type", &self.res_type);
+        w.opt("con-type", &self.con_type);
+        w.bibls(&self.bibl);
+        w.opt("ans-proc", &self.ans_proc);
+        w.list("referral", &self.referral);
+        w.opt("pre-res", &self.pre_res);
+        w.opt("note", &self.note);
+        w.opt("ptn-type", &self.ptn_type);
+        w.list("contri", &self.contri);
+        self.system.write_xml(w);
+        w.text("url", &self.url);
+        w.end("reference");
+    }
+}
+
+impl WriteXml for Manual {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("manual");
+        w.text("theme", &self.theme);
+        w.text("reg-id", &self.reg_id);
+        w.text("guide", &self.guide);
+        w.date("crt-date", self.crt_date);
+        w.bool("completion", self.completion, "1", "2");
+        w.list("keyword", &self.keyword);
+        w.classes(&self.class);
+        w.bibls(&self.bibl);
+        w.opt("note", &self.note);
+        self.system.write_xml(w);
+        w.text("url", &self.url);
+        w.end("manual");
+    }
+}
+
+impl WriteXml for Collection {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("collection");
+        w.text("col-name", &self.col_name);
+        w.text("pro-key", &self.pro_key);
+        w.text("reg-id", &self.reg_id);
+        w.text("outline", &self.outline);
+        w.opt("origin", &self.origin);
+        w.opt("restriction", &self.restriction);
+        w.opt("catalog", &self.catalog);
+        w.opt("literature", &self.literature);
+        w.opt("number", &self.number);
+        w.bool("continue", self.collection_continue, "0", "1");
+        w.list("keyword", &self.keyword);
+        w.classes(&self.class);
+        w.opt("note", &self.note);
+        self.system.write_xml(w);
+        w.text("url", &self.url);
+        w.end("collection");
+    }
+}
+
+impl WriteXml for Profile {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("profile");
+        w.text("lib-type", self.lib_type.code());
+        w.text("lib-name", &self.lib_name);
+        w.text("abbr", &self.abbr);
+        w.text("pro-key", &self.pro_key);
+        w.text("zip-code", &self.zip_code);
+        w.text("add-pref", &self.add_pref);
+        w.text("add-city", &self.add_city);
+        w.text("add-street", &self.add_street);
+        w.text("tel1", &self.tel1);
+        w.opt("tel1-note", &self.tel1_note);
+        w.opt("tel2", &self.tel2);
+        w.opt("tel2-note", &self.tel2_note);
+        w.opt("tel3", &self.tel3);
+        w.opt("tel3-note", &self.tel3_note);
+        w.opt("fax", &self.fax);
+        w.opt("e-mail", &self.e_mail);
+        w.opt("lib-url", &self.lib_url);
+        w.opt("open-info", &self.open_info);
+        w.opt("restriction", &self.restriction);
+        w.opt("outline", &self.outline);
+        w.opt("feature", &self.feature);
+        w.opt("notes", &self.notes);
+        w.opt("access", &self.access);
+        w.opt("isil", &self.isil);
+        self.system.write_xml(w);
+        w.text("url", &self.url);
+        w.end("profile");
+    }
+}
+
+impl WriteXml for Class {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        write!(w.0, r#"<class type="{}""#, escape(&self.class_type)).unwrap();
+        if let Some(version) = &self.version {
+            write!(w.0, r#" version="{}""#, escape(version)).unwrap();
+        }
+        write!(w.0, ">{}</class>", escape(&self.class)).unwrap();
+    }
+}
+
+impl WriteXml for Bibl {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("bibl");
+        w.opt("bibl-desc", &self.bibl_desc);
+        w.opt("bibl-isbn", &self.bibl_isbn);
+        w.opt("bibl-note", &self.bibl_note);
+        w.end("bibl");
+    }
+}
+
+impl WriteXml for System {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("system");
+        w.datetime("reg-date", self.reg_date);
+        w.datetime("lst-date", self.lst_date);
+        w.text("sys-id", &self.sys_id);
+        w.text("lib-id", &self.lib_id);
+        w.text("lib-name", &self.lib_name);
+        w.text("file-num", &self.file_num.to_string());
+        w.end("system");
+    }
+}
+
+impl WriteXml for LibSystem {
+    fn write_xml(&self, w: &mut XmlWriter) {
+        w.start("system");
+        w.datetime("reg-date", self.reg_date);
+        w.datetime("lst-date", self.lst_date);
+        w.text("lib-id", &self.lib_id);
+        w.text("lib-name", &self.lib_name);
+        w.text("file-num", &self.file_num.to_string());
+        w.end("system");
+    }
+}
@@ -0,0 +1,688 @@
+//! SQLiteによる事例の保存
+//!
+//! `sqlite` featureが必要
+//!
+//! 事例を検索区分ごとのテーブルに, キーワードや分類などの複数の値を持つ項目を別のテーブルに保存する.
+//! 事例はシステムID (参加館プロファイルは図書館コード) で一意となり,
+//! 保存済みの事例より最終更新日時が新しいか同じ場合のみ更新する
+//!
+//! # Example
+//!
+//! ```no_run
+//! use crd_api::{
+//!     client::Client,
+//!     harvest::{HarvestState, Harvester},
+//!     request::SearchType,
+//!     sqlite::Store,
+//! };
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let mut store = Store::open("crd.sqlite3")?;
+//!     let mut harvester = Harvester::new(Client::new()?, HarvestState::default());
+//!     harvester
+//!         .harvest(SearchType::Reference, |item| store.upsert(&item).map(|_| ()))
+//!         .await?;
+//!     let references = store.items(SearchType::Reference)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+
+use crate::{
+    request::SearchType,
+    response::{
+        Bibl, Class, Collection, LibSystem, LibType, Manual, Profile, Reference, ResultItem, System,
+    },
+};
+
+const SCHEMA: &str = include_str!("sqlite/schema.sql");
+
+/// 複数の値を持つ項目のテーブル
+const LIST_TABLES: [&str; 5] = ["keyword", "referral", "contri", "class", "bibl"];
+
+/// 事例を保存するSQLiteデータベース
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    /// ファイルを開く
+    ///
+    /// ファイルやテーブルが存在しない場合は作成する
+    ///
+    /// # Errors
+    ///
+    /// データベースを開けないとき, またはテーブルの作成に失敗したときエラーを返す
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, rusqlite::Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// メモリ上のデータベースを作成する
+    ///
+    /// # Errors
+    ///
+    /// テーブルの作成に失敗したときエラーを返す
+    pub fn open_in_memory() -> Result<Self, rusqlite::Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// 接続済みのデータベースを使用する
+    ///
+    /// # Errors
+    ///
+    /// テーブルの作成に失敗したときエラーを返す
+    pub fn from_connection(conn: Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// データベースへの接続
+    ///
+    /// 保存した事例に対して任意のクエリーを実行できる
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// 事例を追加または更新する
+    ///
+    /// 保存済みの事例の最終更新日時の方が新しい場合は更新せずに [`false`] を返す
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したときエラーを返す
+    pub fn upsert(&mut self, item: &ResultItem) -> Result<bool, rusqlite::Error> {
+        let kind = item.search_type();
+        let id = item.id();
+        let tx = self.conn.transaction()?;
+        let sql = format!("SELECT lst_date FROM {kind} WHERE {} = ?1", key(kind));
+        let lst_date: Option<chrono::NaiveDateTime> =
+            tx.query_row(&sql, [id], |row| row.get(0)).optional()?;
+        if lst_date.is_some_and(|d| d > item.lst_date()) {
+            return Ok(false);
+        }
+        for table in LIST_TABLES {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE kind = ?1 AND id = ?2"),
+                params![kind.as_str(), id],
+            )?;
+        }
+        match item {
+            ResultItem::Reference(r) => insert_reference(&tx, r)?,
+            ResultItem::Manual(m) => insert_manual(&tx, m)?,
+            ResultItem::Collection(c) => insert_collection(&tx, c)?,
+            ResultItem::Profile(p) => insert_profile(&tx, p)?,
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// 事例を削除する
+    ///
+    /// 削除したなら [`true`] を返す
+    ///
+    /// # Errors
+    ///
+    /// 書き込みに失敗したときエラーを返す
+    pub fn remove(&mut self, search_type: SearchType, id: &str) -> Result<bool, rusqlite::Error> {
+        let Some(kind) = table(search_type) else {
+            return Ok(false);
+        };
+        let tx = self.conn.transaction()?;
+        for table in LIST_TABLES {
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE kind = ?1 AND id = ?2"),
+                params![kind, id],
+            )?;
+        }
+        let n = tx.execute(
+            &format!("DELETE FROM {kind} WHERE {} = ?1", key(search_type)),
+            [id],
+        )?;
+        tx.commit()?;
+        Ok(n > 0)
+    }
+
+    /// 保存した事例を読み込む
+    ///
+    /// [`SearchType::All`] の場合は全ての検索区分から探す
+    ///
+    /// # Errors
+    ///
+    /// 読み込みに失敗したときエラーを返す
+    pub fn get(
+        &self,
+        search_type: SearchType,
+        id: &str,
+    ) -> Result<Option<ResultItem>, rusqlite::Error> {
+        for kind in kinds(search_type) {
+            let sql = format!("SELECT * FROM {kind} WHERE {} = ?1", key(*kind));
+            let mut stmt = self.conn.prepare_cached(&sql)?;
+            let mut rows = stmt.query([id])?;
+            if let Some(row) = rows.next()? {
+                return self.item(*kind, row).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// 保存した全ての事例を最終更新日時の古い順に読み込む
+    ///
+    /// [`SearchType::All`] の場合は検索区分ごとに読み込む
+    ///
+    /// # Errors
+    ///
+    /// 読み込みに失敗したときエラーを返す
+    pub fn items(&self, search_type: SearchType) -> Result<Vec<ResultItem>, rusqlite::Error> {
+        let mut items = Vec::new();
+        for kind in kinds(search_type) {
+            let sql = format!("SELECT * FROM {kind} ORDER BY lst_date, {}", key(*kind));
+            let mut stmt = self.conn.prepare(&sql)?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                items.push(self.item(*kind, row)?);
+            }
+        }
+        Ok(items)
+    }
+
+    /// 保存した事例の件数
+    ///
+    /// # Errors
+    ///
+    /// 読み込みに失敗したときエラーを返す
+    pub fn count(&self, search_type: SearchType) -> Result<usize, rusqlite::Error> {
+        kinds(search_type).iter().try_fold(0, |n, kind| {
+            let count: usize =
+                self.conn
+                    .query_row(&format!("SELECT COUNT(*) FROM {kind}"), [], |row| {
+                        row.get(0)
+                    })?;
+            Ok(n + count)
+        })
+    }
+
+    fn item(&self, kind: SearchType, row: &Row) -> Result<ResultItem, rusqlite::Error> {
+        Ok(match kind {
+            SearchType::Reference => ResultItem::Reference(self.reference(row)?),
+            SearchType::Manual => ResultItem::Manual(self.manual(row)?),
+            SearchType::Collection => ResultItem::Collection(self.collection(row)?),
+            SearchType::Profile | SearchType::All => ResultItem::Profile(self.profile(row)?),
+        })
+    }
+
+    fn reference(&self, row: &Row) -> Result<Reference, rusqlite::Error> {
+        let system = system(row)?;
+        let lists = Lists::new(self, SearchType::Reference, &system.sys_id);
+        Ok(Reference {
+            question: row.get("question")?,
+            reg_id: row.get("reg_id")?,
+            answer: row.get("answer")?,
+            crt_date: row.get("crt_date")?,
+            solution: row.get("solution")?,
+            keyword: lists.strings("keyword")?,
+            class: lists.classes()?,
+            res_type: row.get("res_type")?,
+            con_type: row.get("con_type")?,
+            bibl: lists.bibls()?,
+            ans_proc: row.get("ans_proc")?,
+            referral: lists.strings("referral")?,
+            pre_res: row.get("pre_res")?,
+            note: row.get("note")?,
+            ptn_type: row.get("ptn_type")?,
+            contri: lists.strings("contri")?,
+            url: row.get("url")?,
+            system,
+        })
+    }
+
+    fn manual(&self, row: &Row) -> Result<Manual, rusqlite::Error> {
+        let system = system(row)?;
+        let lists = Lists::new(self, SearchType::Manual, &system.sys_id);
+        Ok(Manual {
+            theme: row.get("theme")?,
+            reg_id: row.get("reg_id")?,
+            guide: row.get("guide")?,
+            crt_date: row.get("crt_date")?,
+            completion: row.get("completion")?,
+            keyword: lists.strings("keyword")?,
+            class: lists.classes()?,
+            bibl: lists.bibls()?,
+            note: row.get("note")?,
+            url: row.get("url")?,
+            system,
+        })
+    }
+
+    fn collection(&self, row: &Row) -> Result<Collection, rusqlite::Error> {
+        let system = system(row)?;
+        let lists = Lists::new(self, SearchType::Collection, &system.sys_id);
+        Ok(Collection {
+            col_name: row.get("col_name")?,
+            pro_key: row.get("pro_key")?,
+            reg_id: row.get("reg_id")?,
+            outline: row.get("outline")?,
+            origin: row.get("origin")?,
+            restriction: row.get("restriction")?,
+            catalog: row.get("catalog")?,
+            literature: row.get("literature")?,
+            number: row.get("number")?,
+            collection_continue: row.get("collection_continue")?,
+            keyword: lists.strings("keyword")?,
+            class: lists.classes()?,
+            note: row.get("note")?,
+            url: row.get("url")?,
+            system,
+        })
+    }
+
+    fn profile(&self, row: &Row) -> Result<Profile, rusqlite::Error> {
+        Ok(Profile {
+            lib_type: LibType::from_code(&row.get::<_, String>("lib_type")?),
+            lib_name: row.get("lib_name")?,
+            abbr: row.get("abbr")?,
+            pro_key: row.get("pro_key")?,
+            zip_code: row.get("zip_code")?,
+            add_pref: row.get("add_pref")?,
+            add_city: row.get("add_city")?,
+            add_street: row.get("add_street")?,
+            tel1: row.get("tel1")?,
+            tel1_note: row.get("tel1_note")?,
+            tel2: row.get("tel2")?,
+            tel2_note: row.get("tel2_note")?,
+            tel3: row.get("tel3")?,
+            tel3_note: row.get("tel3_note")?,
+            fax: row.get("fax")?,
+            e_mail: row.get("e_mail")?,
+            lib_url: row.get("lib_url")?,
+            open_info: row.get("open_info")?,
+            restriction: row.get("restriction")?,
+            outline: row.get("outline")?,
+            feature: row.get("feature")?,
+            notes: row.get("notes")?,
+            access: row.get("access")?,
+            isil: row.get("isil")?,
+            system: LibSystem {
+                reg_date: row.get("reg_date")?,
+                lst_date: row.get("lst_date")?,
+                lib_id: row.get("lib_id")?,
+                lib_name: row.get("system_lib_name")?,
+                file_num: row.get("file_num")?,
+            },
+            url: row.get("url")?,
+        })
+    }
+}
+
+/// 検索区分のテーブル名
+fn table(search_type: SearchType) -> Option<&'static str> {
+    match search_type {
+        SearchType::All => None,
+        t => Some(t.as_str()),
+    }
+}
+
+/// 検索区分の主キー
+fn key(search_type: SearchType) -> &'static str {
+    match search_type {
+        SearchType::Profile => "lib_id",
+        _ => "sys_id",
+    }
+}
+
+/// 検索区分に含まれるテーブルの検索区分
+fn kinds(search_type: SearchType) -> &'static [SearchType] {
+    use SearchType::*;
+    match search_type {
+        Reference => &[Reference],
+        Manual => &[Manual],
+        Collection => &[Collection],
+        Profile => &[Profile],
+        All => &[Reference, Manual, Collection, Profile],
+    }
+}
+
+fn system(row: &Row) -> Result<System, rusqlite::Error> {
+    Ok(System {
+        reg_date: row.get("reg_date")?,
+        lst_date: row.get("lst_date")?,
+        sys_id: row.get("sys_id")?,
+        lib_id: row.get("lib_id")?,
+        lib_name: row.get("lib_name")?,
+        file_num: row.get("file_num")?,
+    })
+}
+
+/// 事例の複数の値を持つ項目の読み込み
+struct Lists<'a> {
+    conn: &'a Connection,
+    kind: &'static str,
+    id: &'a str,
+}
+
+impl<'a> Lists<'a> {
+    fn new(store: &'a Store, kind: SearchType, id: &'a str) -> Self {
+        Self {
+            conn: &store.conn,
+            kind: kind.as_str(),
+            id,
+        }
+    }
+
+    /// 値がない場合は [`None`]
+    fn query<T>(
+        &self,
+        table: &str,
+        columns: &str,
+        f: impl Fn(&Row) -> Result<T, rusqlite::Error>,
+    ) -> Result<Option<Vec<T>>, rusqlite::Error> {
+        let sql = format!("SELECT {columns} FROM {table} WHERE kind = ?1 AND id = ?2 ORDER BY seq");
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let values = stmt
+            .query_map(params![self.kind, self.id], |row| f(row))?
+            .collect::<Result<Vec<T>, _>>()?;
+        Ok(Some(values).filter(|v| !v.is_empty()))
+    }
+
+    fn strings(&self, table: &str) -> Result<Option<Vec<String>>, rusqlite::Error> {
+        self.query(table, "value", |row| row.get(0))
+    }
+
+    fn classes(&self) -> Result<Option<Vec<Class>>, rusqlite::Error> {
+        self.query("class", "type, version, value", |row| {
+            Ok(Class {
+                class_type: row.get(0)?,
+                version: row.get(1)?,
+                class: row.get(2)?,
+            })
+        })
+    }
+
+    fn bibls(&self) -> Result<Option<Vec<Bibl>>, rusqlite::Error> {
+        self.query("bibl", "bibl_desc, bibl_isbn, bibl_note", |row| {
+            Ok(Bibl {
+                bibl_desc: row.get(0)?,
+                bibl_isbn: row.get(1)?,
+                bibl_note: row.get(2)?,
+            })
+        })
+    }
+}
+
+fn insert_strings(
+    tx: &Transaction,
+    table: &str,
+    kind: SearchType,
+    id: &str,
+    values: &Option<Vec<String>>,
+) -> Result<(), rusqlite::Error> {
+    let sql = format!("INSERT INTO {table} (kind, id, seq, value) VALUES (?1, ?2, ?3, ?4)");
+    let mut stmt = tx.prepare_cached(&sql)?;
+    for (seq, value) in values.iter().flatten().enumerate() {
+        stmt.execute(params![kind.as_str(), id, seq, value])?;
+    }
+    Ok(())
+}
+
+fn insert_classes(
+    tx: &Transaction,
+    kind: SearchType,
+    id: &str,
+    classes: &Option<Vec<Class>>,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO class (kind, id, seq, type, version, value) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (seq, c) in classes.iter().flatten().enumerate() {
+        stmt.execute(params![
+            kind.as_str(),
+            id,
+            seq,
+            c.class_type,
+            c.version,
+            c.class
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_bibls(
+    tx: &Transaction,
+    kind: SearchType,
+    id: &str,
+    bibls: &Option<Vec<Bibl>>,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT INTO bibl (kind, id, seq, bibl_desc, bibl_isbn, bibl_note) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (seq, b) in bibls.iter().flatten().enumerate() {
+        stmt.execute(params![
+            kind.as_str(),
+            id,
+            seq,
+            b.bibl_desc,
+            b.bibl_isbn,
+            b.bibl_note
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_reference(tx: &Transaction, r: &Reference) -> Result<(), rusqlite::Error> {
+    let s = &r.system;
+    tx.execute(
+        "INSERT OR REPLACE INTO reference (
+            sys_id, question, reg_id, answer, crt_date, solution, res_type, con_type, ans_proc,
+            pre_res, note, ptn_type, reg_date, lst_date, lib_id, lib_name, file_num, url
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            s.sys_id, r.question, r.reg_id, r.answer, r.crt_date, r.solution, r.res_type,
+            r.con_type, r.ans_proc, r.pre_res, r.note, r.ptn_type, s.reg_date, s.lst_date,
+            s.lib_id, s.lib_name, s.file_num, r.url,
+        ],
+    )?;
+    let kind = SearchType::Reference;
+    insert_strings(tx, "keyword", kind, &s.sys_id, &r.keyword)?;
+    insert_strings(tx, "referral", kind, &s.sys_id, &r.referral)?;
+    insert_strings(tx, "contri", kind, &s.sys_id, &r.contri)?;
+    insert_classes(tx, kind, &s.sys_id, &r.class)?;
+    insert_bibls(tx, kind, &s.sys_id, &r.bibl)
+}
+
+fn insert_manual(tx: &Transaction, m: &Manual) -> Result<(), rusqlite::Error> {
+    let s = &m.system;
+    tx.execute(
+        "INSERT OR REPLACE INTO manual (
+            sys_id, theme, reg_id, guide, crt_date, completion, note,
+            reg_date, lst_date, lib_id, lib_name, file_num, url
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            s.sys_id,
+            m.theme,
+            m.reg_id,
+            m.guide,
+            m.crt_date,
+            m.completion,
+            m.note,
+            s.reg_date,
+            s.lst_date,
+            s.lib_id,
+            s.lib_name,
+            s.file_num,
+            m.url,
+        ],
+    )?;
+    let kind = SearchType::Manual;
+    insert_strings(tx, "keyword", kind, &s.sys_id, &m.keyword)?;
+    insert_classes(tx, kind, &s.sys_id, &m.class)?;
+    insert_bibls(tx, kind, &s.sys_id, &m.bibl)
+}
+
+fn insert_collection(tx: &Transaction, c: &Collection) -> Result<(), rusqlite::Error> {
+    let s = &c.system;
+    tx.execute(
+        "INSERT OR REPLACE INTO collection (
+            sys_id, col_name, pro_key, reg_id, outline, origin, restriction, catalog, literature,
+            number, collection_continue, note, reg_date, lst_date, lib_id, lib_name, file_num, url
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            s.sys_id,
+            c.col_name,
+            c.pro_key,
+            c.reg_id,
+            c.outline,
+            c.origin,
+            c.restriction,
+            c.catalog,
+            c.literature,
+            c.number,
+            c.collection_continue,
+            c.note,
+            s.reg_date,
+            s.lst_date,
+            s.lib_id,
+            s.lib_name,
+            s.file_num,
+            c.url,
+        ],
+    )?;
+    let kind = SearchType::Collection;
+    insert_strings(tx, "keyword", kind, &s.sys_id, &c.keyword)?;
+    insert_classes(tx, kind, &s.sys_id, &c.class)
+}
+
+fn insert_profile(tx: &Transaction, p: &Profile) -> Result<(), rusqlite::Error> {
+    let s = &p.system;
+    tx.execute(
+        "INSERT OR REPLACE INTO profile (
+            lib_id, lib_type, lib_name, abbr, pro_key, zip_code, add_pref, add_city, add_street,
+            tel1, tel1_note, tel2, tel2_note, tel3, tel3_note, fax, e_mail, lib_url, open_info,
+            restriction, outline, feature, notes, access, isil,
+            reg_date, lst_date, system_lib_name, file_num, url
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+            ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30
+        )",
+        params![
+            s.lib_id,
+            p.lib_type.code(),
+            p.lib_name,
+            p.abbr,
+            p.pro_key,
+            p.zip_code,
+            p.add_pref,
+            p.add_city,
+            p.add_street,
+            p.tel1,
+            p.tel1_note,
+            p.tel2,
+            p.tel2_note,
+            p.tel3,
+            p.tel3_note,
+            p.fax,
+            p.e_mail,
+            p.lib_url,
+            p.open_info,
+            p.restriction,
+            p.outline,
+            p.feature,
+            p.notes,
+            p.access,
+            p.isil,
+            s.reg_date,
+            s.lst_date,
+            s.lib_name,
+            s.file_num,
+            p.url,
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Corpus;
+
+    #[test]
+    fn round_trip_test() {
+        let corpus = Corpus::fixtures();
+        let mut store = Store::open_in_memory().unwrap();
+        for item in corpus.items() {
+            assert!(store.upsert(item).unwrap());
+        }
+        assert_eq!(store.count(SearchType::All).unwrap(), corpus.items().len());
+        assert_eq!(store.count(SearchType::Profile).unwrap(), 4);
+        for item in corpus.items() {
+            assert_eq!(
+                store.get(item.search_type(), item.id()).unwrap().as_ref(),
+                Some(item)
+            );
+            assert_eq!(
+                store.get(SearchType::All, item.id()).unwrap().as_ref(),
+                Some(item)
+            );
+        }
+        let references = store.items(SearchType::Reference).unwrap();
+        let ids: Vec<_> = references.iter().map(ResultItem::id).collect();
+        assert_eq!(
+            ids,
+            ["1000000003", "1000000001", "1000000002", "1000000004"]
+        );
+
+        let keywords: usize = store
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM keyword WHERE value = '地図'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(keywords, 3);
+    }
+
+    #[test]
+    fn upsert_test() {
+        let corpus = Corpus::fixtures();
+        let ResultItem::Reference(original) = &corpus.items()[0] else {
+            panic!("expected reference");
+        };
+        let mut store = Store::open_in_memory().unwrap();
+        assert!(store
+            .upsert(&ResultItem::Reference(original.clone()))
+            .unwrap());
+
+        // 最終更新日時が新しい事例で更新する
+        let mut updated = original.clone();
+        updated.keyword = Some(vec!["更新".to_string()]);
+        updated.bibl = None;
+        updated.system.lst_date += chrono::Duration::days(1);
+        assert!(store
+            .upsert(&ResultItem::Reference(updated.clone()))
+            .unwrap());
+        let id = &original.system.sys_id;
+        assert_eq!(
+            store.get(SearchType::Reference, id).unwrap(),
+            Some(ResultItem::Reference(updated.clone()))
+        );
+
+        // 古い事例では更新しない
+        assert!(!store
+            .upsert(&ResultItem::Reference(original.clone()))
+            .unwrap());
+        assert_eq!(
+            store.get(SearchType::Reference, id).unwrap(),
+            Some(ResultItem::Reference(updated))
+        );
+
+        assert!(store.remove(SearchType::Reference, id).unwrap());
+        assert!(!store.remove(SearchType::Reference, id).unwrap());
+        assert_eq!(store.get(SearchType::Reference, id).unwrap(), None);
+        assert_eq!(store.count(SearchType::All).unwrap(), 0);
+    }
+}
@@ -0,0 +1,140 @@
+CREATE TABLE IF NOT EXISTS reference (
+    sys_id TEXT PRIMARY KEY NOT NULL,
+    question TEXT NOT NULL,
+    reg_id TEXT NOT NULL,
+    answer TEXT NOT NULL,
+    crt_date TEXT,
+    solution INTEGER,
+    res_type TEXT,
+    con_type TEXT,
+    ans_proc TEXT,
+    pre_res TEXT,
+    note TEXT,
+    ptn_type TEXT,
+    reg_date TEXT NOT NULL,
+    lst_date TEXT NOT NULL,
+    lib_id TEXT NOT NULL,
+    lib_name TEXT NOT NULL,
+    file_num INTEGER NOT NULL,
+    url TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS manual (
+    sys_id TEXT PRIMARY KEY NOT NULL,
+    theme TEXT NOT NULL,
+    reg_id TEXT NOT NULL,
+    guide TEXT NOT NULL,
+    crt_date TEXT,
+    completion INTEGER,
+    note TEXT,
+    reg_date TEXT NOT NULL,
+    lst_date TEXT NOT NULL,
+    lib_id TEXT NOT NULL,
+    lib_name TEXT NOT NULL,
+    file_num INTEGER NOT NULL,
+    url TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS collection (
+    sys_id TEXT PRIMARY KEY NOT NULL,
+    col_name TEXT NOT NULL,
+    pro_key TEXT NOT NULL,
+    reg_id TEXT NOT NULL,
+    outline TEXT NOT NULL,
+    origin TEXT,
+    restriction TEXT,
+    catalog TEXT,
+    literature TEXT,
+    number TEXT,
+    collection_continue INTEGER,
+    note TEXT,
+    reg_date TEXT NOT NULL,
+    lst_date TEXT NOT NULL,
+    lib_id TEXT NOT NULL,
+    lib_name TEXT NOT NULL,
+    file_num INTEGER NOT NULL,
+    url TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS profile (
+    lib_id TEXT PRIMARY KEY NOT NULL,
+    lib_type TEXT NOT NULL,
+    lib_name TEXT NOT NULL,
+    abbr TEXT NOT NULL,
+    pro_key TEXT NOT NULL,
+    zip_code TEXT NOT NULL,
+    add_pref TEXT NOT NULL,
+    add_city TEXT NOT NULL,
+    add_street TEXT NOT NULL,
+    tel1 TEXT NOT NULL,
+    tel1_note TEXT,
+    tel2 TEXT,
+    tel2_note TEXT,
+    tel3 TEXT,
+    tel3_note TEXT,
+    fax TEXT,
+    e_mail TEXT,
+    lib_url TEXT,
+    open_info TEXT,
+    restriction TEXT,
+    outline TEXT,
+    feature TEXT,
+    notes TEXT,
+    access TEXT,
+    isil TEXT,
+    reg_date TEXT NOT NULL,
+    lst_date TEXT NOT NULL,
+    system_lib_name TEXT NOT NULL,
+    file_num INTEGER NOT NULL,
+    url TEXT NOT NULL
+);
+
+-- 複数の値を持つ項目. kind は検索区分, id はシステムID (参加館プロファイルは図書館コード)
+CREATE TABLE IF NOT EXISTS keyword (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (kind, id, seq)
+);
+
+CREATE TABLE IF NOT EXISTS referral (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (kind, id, seq)
+);
+
+CREATE TABLE IF NOT EXISTS contri (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (kind, id, seq)
+);
+
+CREATE TABLE IF NOT EXISTS class (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    type TEXT NOT NULL,
+    version TEXT,
+    value TEXT NOT NULL,
+    PRIMARY KEY (kind, id, seq)
+);
+
+CREATE TABLE IF NOT EXISTS bibl (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    bibl_desc TEXT,
+    bibl_isbn TEXT,
+    bibl_note TEXT,
+    PRIMARY KEY (kind, id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS reference_lst_date ON reference (lst_date);
+CREATE INDEX IF NOT EXISTS manual_lst_date ON manual (lst_date);
+CREATE INDEX IF NOT EXISTS collection_lst_date ON collection (lst_date);
+CREATE INDEX IF NOT EXISTS profile_lst_date ON profile (lst_date);